@@ -1,4 +1,4 @@
-use std::borrow::Cow;
+use alloc::{borrow::Cow, string::String, vec::Vec};
 
 use super::{
     decode_percents, decode_percents_str, transport, transport::TransportImpl, Error, Guid,
@@ -44,6 +44,39 @@ impl<'a> DBusAddr<'a> {
         }
     }
 
+    /// Check that something listens at this address, with the default [`ProbeOptions`].
+    ///
+    /// [`ProbeOptions`]: crate::ProbeOptions
+    #[cfg(feature = "std")]
+    pub fn probe(&self) -> crate::ProbeReport {
+        self.probe_with(&crate::ProbeOptions::default())
+    }
+
+    /// Check that something listens at this address.
+    ///
+    /// The address is resolved and connected to. Unless disabled in the options, the initial NUL
+    /// byte and an `AUTH` command are then sent, to check that a D-Bus server answers.
+    #[cfg(feature = "std")]
+    pub fn probe_with(&self, options: &crate::ProbeOptions) -> crate::ProbeReport {
+        crate::probe::probe(self, options)
+    }
+
+    /// Connect to this address, with the default [`ProbeOptions`].
+    ///
+    /// [`ProbeOptions`]: crate::ProbeOptions
+    #[cfg(feature = "std")]
+    pub fn connect(&self) -> Result<crate::DBusStream> {
+        self.connect_with(&crate::ProbeOptions::default())
+    }
+
+    /// Connect to this address.
+    ///
+    /// The `auth` option is not used: this is a probe without authentication.
+    #[cfg(feature = "std")]
+    pub fn connect_with(&self, options: &crate::ProbeOptions) -> Result<crate::DBusStream> {
+        crate::probe::connect_addr(self, options)
+    }
+
     pub(super) fn key_val_iter(&'a self) -> KeyValIter<'a> {
         let mut split = self.addr.splitn(2, ':');
         // skip transport:..
@@ -114,32 +147,32 @@ pub trait ToDBusAddrs<'a> {
 }
 
 impl<'a> ToDBusAddrs<'a> for DBusAddr<'a> {
-    type Iter = std::iter::Once<Result<DBusAddr<'a>>>;
+    type Iter = core::iter::Once<Result<DBusAddr<'a>>>;
 
     /// Get an iterator over the D-Bus addresses.
     fn to_dbus_addrs(&'a self) -> Self::Iter {
-        std::iter::once(Ok(self.clone()))
+        core::iter::once(Ok(self.clone()))
     }
 }
 
 impl<'a> ToDBusAddrs<'a> for str {
-    type Iter = std::iter::Once<Result<DBusAddr<'a>>>;
+    type Iter = core::iter::Once<Result<DBusAddr<'a>>>;
 
     fn to_dbus_addrs(&'a self) -> Self::Iter {
-        std::iter::once(self.try_into())
+        core::iter::once(self.try_into())
     }
 }
 
 impl<'a> ToDBusAddrs<'a> for String {
-    type Iter = std::iter::Once<Result<DBusAddr<'a>>>;
+    type Iter = core::iter::Once<Result<DBusAddr<'a>>>;
 
     fn to_dbus_addrs(&'a self) -> Self::Iter {
-        std::iter::once(self.as_str().try_into())
+        core::iter::once(self.as_str().try_into())
     }
 }
 
 impl<'a> ToDBusAddrs<'a> for Vec<Result<DBusAddr<'_>>> {
-    type Iter = std::iter::Cloned<std::slice::Iter<'a, Result<DBusAddr<'a>>>>;
+    type Iter = core::iter::Cloned<core::slice::Iter<'a, Result<DBusAddr<'a>>>>;
 
     fn to_dbus_addrs(&'a self) -> Self::Iter {
         self.iter().cloned()
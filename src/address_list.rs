@@ -1,4 +1,9 @@
-use std::{borrow::Cow, fmt};
+#[cfg(any(test, feature = "arbitrary", feature = "proptest"))]
+use alloc::string::ToString;
+#[cfg(any(test, feature = "std", feature = "arbitrary", feature = "proptest"))]
+use alloc::vec::Vec;
+use alloc::{borrow::Cow, string::String};
+use core::fmt;
 
 use super::{DBusAddr, Error, OwnedDBusAddr, Result, ToDBusAddrs, ToOwnedDBusAddrs};
 
@@ -10,6 +15,88 @@ pub struct DBusAddrList<'a> {
     addr: Cow<'a, str>,
 }
 
+impl DBusAddrList<'_> {
+    // The `;`-separated list of the given addresses.
+    #[cfg(any(test, feature = "arbitrary", feature = "proptest"))]
+    pub(crate) fn from_addrs(addrs: &[OwnedDBusAddr]) -> DBusAddrList<'static> {
+        let addrs: Vec<String> = addrs.iter().map(|a| a.to_string()).collect();
+
+        DBusAddrList {
+            addr: Cow::Owned(addrs.join(";")),
+        }
+    }
+
+    // Check that the list is not empty, and that every address is valid.
+    pub(crate) fn validate(&self) -> Result<()> {
+        let mut empty = true;
+        for addr in self.to_dbus_addrs() {
+            addr?;
+            empty = false;
+        }
+        if empty {
+            return Err(Error::MissingTransport);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl DBusAddrList<'_> {
+    /// Probe each address of the list, with the default [`ProbeOptions`].
+    ///
+    /// [`ProbeOptions`]: crate::ProbeOptions
+    pub fn probe(&self) -> Vec<crate::ProbeReport> {
+        self.probe_with(&crate::ProbeOptions::default())
+    }
+
+    /// Probe each address of the list.
+    ///
+    /// See [`DBusAddr::probe_with`]. Entries that fail to parse are reported with the parse error.
+    pub fn probe_with(&self, options: &crate::ProbeOptions) -> Vec<crate::ProbeReport> {
+        let mut reports = Vec::new();
+        let mut iter = DBusAddrListIter::new(self);
+        loop {
+            let start = iter.next_index;
+            let Some(addr) = iter.next() else {
+                break;
+            };
+            reports.push(match addr {
+                Ok(addr) => addr.probe_with(options),
+                Err(e) => {
+                    let entry = &iter.data[start..iter.next_index];
+                    let entry = entry.strip_suffix(';').unwrap_or(entry);
+                    crate::ProbeReport::failed(entry, e)
+                }
+            });
+        }
+
+        reports
+    }
+
+    /// Connect to the first working address of the list, with the default [`ProbeOptions`].
+    ///
+    /// [`ProbeOptions`]: crate::ProbeOptions
+    pub fn connect(&self) -> Result<crate::DBusStream> {
+        self.connect_with(&crate::ProbeOptions::default())
+    }
+
+    /// Connect to the first working address of the list.
+    ///
+    /// See [`DBusAddr::connect_with`]. If no address works, the error of the last one is returned.
+    pub fn connect_with(&self, options: &crate::ProbeOptions) -> Result<crate::DBusStream> {
+        let mut last_err = Error::MissingTransport;
+        for addr in DBusAddrListIter::new(self) {
+            match addr.and_then(|addr| addr.connect_with(options)) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_err = e,
+            }
+        }
+
+        Err(last_err)
+    }
+}
+
 impl<'a> ToDBusAddrs<'a> for DBusAddrList<'a> {
     type Iter = DBusAddrListIter<'a>;
 
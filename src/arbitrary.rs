@@ -0,0 +1,204 @@
+//! [`Arbitrary`] implementations, generating well-formed addresses.
+
+use alloc::{borrow::Cow, collections::BTreeMap, string::String, vec::Vec};
+
+use ::arbitrary::{Arbitrary, Result, Unstructured};
+
+use super::{
+    transport::{
+        Autolaunch, AutolaunchScope, Launchd, MachineExec, MachineRef, MachineUnix, NonceTcp,
+        Systemd, Tcp, TcpFamily, Transport, Unix, UnixAddrKind, Unixexec, Vsock, KNOWN_TRANSPORTS,
+        TRANSPORT_NAME_CHARS,
+    },
+    DBusAddrList, Guid, OwnedDBusAddr,
+};
+
+fn path(u: &mut Unstructured<'_>) -> Result<Cow<'static, str>> {
+    Ok(Cow::Owned(u.arbitrary()?))
+}
+
+impl<'a> Arbitrary<'a> for Guid {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Guid::new(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for TcpFamily {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&[TcpFamily::IPv4, TcpFamily::IPv6])?)
+    }
+}
+
+impl<'a> Arbitrary<'a> for UnixAddrKind<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=4)? {
+            0 => UnixAddrKind::Path(path(u)?),
+            1 => UnixAddrKind::Dir(path(u)?),
+            2 => UnixAddrKind::Tmpdir(path(u)?),
+            3 => UnixAddrKind::Abstract(Cow::Owned(u.arbitrary()?)),
+            _ => UnixAddrKind::Runtime,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Unix<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Unix::new(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Launchd<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Launchd::new(Cow::Owned(u.arbitrary()?)))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Systemd<'_> {
+    fn arbitrary(_u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Systemd::new())
+    }
+}
+
+impl<'a> Arbitrary<'a> for Tcp<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Tcp::new(
+            u.arbitrary::<Option<String>>()?.map(Cow::Owned),
+            u.arbitrary::<Option<String>>()?.map(Cow::Owned),
+            u.arbitrary()?,
+            u.arbitrary()?,
+        ))
+    }
+}
+
+impl<'a> Arbitrary<'a> for NonceTcp<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let noncefile = match u.arbitrary()? {
+            true => Some(path(u)?),
+            false => None,
+        };
+
+        Ok(NonceTcp::new(
+            u.arbitrary::<Option<String>>()?.map(Cow::Owned),
+            u.arbitrary::<Option<String>>()?.map(Cow::Owned),
+            u.arbitrary()?,
+            u.arbitrary()?,
+            noncefile,
+        ))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Unixexec<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let path = path(u)?;
+        // unique and sorted indexes, as when parsed
+        let argv: BTreeMap<usize, String> = u.arbitrary()?;
+        let argv = argv.into_iter().map(|(n, a)| (n, Cow::Owned(a))).collect();
+
+        Ok(Unixexec::new(path, argv))
+    }
+}
+
+impl<'a> Arbitrary<'a> for AutolaunchScope<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=2)? {
+            0 => AutolaunchScope::InstallPath,
+            1 => AutolaunchScope::User,
+            _ => {
+                // the keywords are mapped to the other variants, as when parsed
+                let other: String = u.arbitrary()?;
+                AutolaunchScope::try_from(Cow::Owned(other)).unwrap_or(AutolaunchScope::User)
+            }
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Autolaunch<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Autolaunch::new(u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Vsock<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Vsock::new(u.arbitrary()?, u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for MachineRef<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.arbitrary()? {
+            true => MachineRef::Name(Cow::Owned(u.arbitrary()?)),
+            false => MachineRef::Pid(u.arbitrary()?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for MachineUnix<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let machine = u.arbitrary()?;
+        let path = match u.arbitrary()? {
+            true => Some(path(u)?),
+            false => None,
+        };
+
+        Ok(MachineUnix::new(machine, path))
+    }
+}
+
+impl<'a> Arbitrary<'a> for MachineExec<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let machine = u.arbitrary()?;
+        let path = path(u)?;
+        // unique and sorted indexes, as when parsed
+        let argv: BTreeMap<usize, String> = u.arbitrary()?;
+        let argv = argv.into_iter().map(|(n, a)| (n, Cow::Owned(a))).collect();
+
+        Ok(MachineExec::new(machine, path, argv))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Transport<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.int_in_range(0..=10)? {
+            0 => Transport::Unix(u.arbitrary()?),
+            1 => Transport::Launchd(u.arbitrary()?),
+            2 => Transport::Systemd(u.arbitrary()?),
+            3 => Transport::Tcp(u.arbitrary()?),
+            4 => Transport::NonceTcp(u.arbitrary()?),
+            5 => Transport::Unixexec(u.arbitrary()?),
+            6 => Transport::Autolaunch(u.arbitrary()?),
+            7 => Transport::Vsock(u.arbitrary()?),
+            8 => Transport::MachineUnix(u.arbitrary()?),
+            9 => Transport::MachineExec(u.arbitrary()?),
+            _ => {
+                let len = u.int_in_range(1..=16)?;
+                let mut name = String::with_capacity(len);
+                for _ in 0..len {
+                    name.push(*u.choose(TRANSPORT_NAME_CHARS)? as char);
+                }
+                while KNOWN_TRANSPORTS.contains(&name.as_str()) {
+                    name.insert_str(0, "x-");
+                }
+
+                Transport::Other(Cow::Owned(name))
+            }
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for OwnedDBusAddr {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(OwnedDBusAddr::from_parts(u.arbitrary()?, u.arbitrary()?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for DBusAddrList<'_> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut addrs: Vec<OwnedDBusAddr> = u.arbitrary()?;
+        if addrs.is_empty() {
+            addrs.push(u.arbitrary()?);
+        }
+
+        Ok(DBusAddrList::from_addrs(&addrs))
+    }
+}
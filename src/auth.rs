@@ -0,0 +1,761 @@
+//! D-Bus SASL authentication handshake.
+//!
+//! Once connected, a client sends a NUL byte and authenticates with one of the mechanisms
+//! supported by the server, such as `AUTH EXTERNAL <hex uid>`. The server answers with
+//! `OK <guid>`, the client may negotiate Unix file descriptor passing, and `BEGIN`s the message
+//! stream.
+//!
+//! <https://dbus.freedesktop.org/doc/dbus-specification.html#auth-protocol>
+//!
+//! Example:
+//! ```no_run
+//! use std::os::unix::net::UnixStream;
+//!
+//! use dbus_addr::{auth::AuthClient, DBusAddr};
+//!
+//! let addr = DBusAddr::try_from("unix:path=/run/dbus/system_bus_socket").unwrap();
+//! let mut stream = UnixStream::connect("/run/dbus/system_bus_socket").unwrap();
+//! let auth = AuthClient::for_address(&addr)
+//!     .unwrap()
+//!     .authenticate(&mut stream)
+//!     .unwrap();
+//! println!("connected to {}", auth.guid());
+//! ```
+
+use std::{
+    fmt,
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use super::{
+    keyring::{self, Keyring, DEFAULT_CONTEXT},
+    DBusAddr, Environment, Error, Guid, ProcessEnvironment, Result,
+};
+
+// Longest accepted command line, like libdbus.
+const MAX_LINE: usize = 16 * 1024;
+
+/// A SASL authentication mechanism.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Mechanism {
+    /// Credentials passed out of band, such as the Unix socket peer credentials.
+    External,
+    /// Proof that the client can read the user's keyring, see [`crate::keyring`].
+    CookieSha1,
+    /// No authentication.
+    Anonymous,
+}
+
+impl Mechanism {
+    /// The mechanism name, as sent on the wire.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::External => "EXTERNAL",
+            Self::CookieSha1 => "DBUS_COOKIE_SHA1",
+            Self::Anonymous => "ANONYMOUS",
+        }
+    }
+}
+
+impl fmt::Display for Mechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Mechanism {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "EXTERNAL" => Ok(Self::External),
+            "DBUS_COOKIE_SHA1" => Ok(Self::CookieSha1),
+            "ANONYMOUS" => Ok(Self::Anonymous),
+            _ => Err(Error::Other(format!("unsupported mechanism `{s}`"))),
+        }
+    }
+}
+
+/// The outcome of a successful handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authenticated {
+    guid: Guid,
+    mechanism: Mechanism,
+    unix_fd: bool,
+    uid: Option<u32>,
+}
+
+impl Authenticated {
+    /// The server GUID.
+    pub fn guid(&self) -> &Guid {
+        &self.guid
+    }
+
+    /// The mechanism that succeeded.
+    pub fn mechanism(&self) -> Mechanism {
+        self.mechanism
+    }
+
+    /// Whether Unix file descriptor passing was agreed.
+    pub fn unix_fd(&self) -> bool {
+        self.unix_fd
+    }
+
+    /// On the server side, the user ID authenticated with `EXTERNAL` or `DBUS_COOKIE_SHA1`.
+    ///
+    /// `DBUS_COOKIE_SHA1` only proves that the client can read the server keyring, so the user ID
+    /// is only known if the peer user ID was given with [`AuthServer::peer_uid`].
+    pub fn uid(&self) -> Option<u32> {
+        self.uid
+    }
+}
+
+/// The client side of the handshake.
+#[derive(Debug, Clone)]
+pub struct AuthClient {
+    mechanisms: Vec<Mechanism>,
+    uid: Option<u32>,
+    keyring: Option<Keyring>,
+    unix_fd: bool,
+    guid: Option<Guid>,
+}
+
+impl Default for AuthClient {
+    fn default() -> Self {
+        Self {
+            mechanisms: vec![
+                Mechanism::External,
+                Mechanism::CookieSha1,
+                Mechanism::Anonymous,
+            ],
+            uid: cfg!(unix).then(|| ProcessEnvironment.euid()),
+            keyring: None,
+            unix_fd: false,
+            guid: None,
+        }
+    }
+}
+
+impl AuthClient {
+    /// Create a client trying `EXTERNAL` with the effective user ID, `DBUS_COOKIE_SHA1`, then
+    /// `ANONYMOUS`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A client expecting the server GUID given by the address, if any.
+    pub fn for_address(addr: &DBusAddr<'_>) -> Result<Self> {
+        Ok(Self {
+            guid: addr.guid()?,
+            ..Self::default()
+        })
+    }
+
+    /// Set the mechanisms to try, in order.
+    pub fn mechanisms<M: IntoIterator<Item = Mechanism>>(mut self, mechanisms: M) -> Self {
+        self.mechanisms = mechanisms.into_iter().collect();
+        self
+    }
+
+    /// Set the user ID claimed with `EXTERNAL` and `DBUS_COOKIE_SHA1`.
+    ///
+    /// If unset, the server derives it from the credentials with `EXTERNAL`.
+    pub fn uid(mut self, uid: Option<u32>) -> Self {
+        self.uid = uid;
+        self
+    }
+
+    /// Set the keyring used with `DBUS_COOKIE_SHA1`, [`Keyring::new`] by default.
+    pub fn keyring(mut self, keyring: Option<Keyring>) -> Self {
+        self.keyring = keyring;
+        self
+    }
+
+    /// Whether to negotiate Unix file descriptor passing.
+    pub fn negotiate_unix_fd(mut self, unix_fd: bool) -> Self {
+        self.unix_fd = unix_fd;
+        self
+    }
+
+    /// Set the expected server GUID.
+    pub fn guid(mut self, guid: Option<Guid>) -> Self {
+        self.guid = guid;
+        self
+    }
+
+    /// Run the handshake, up to and including `BEGIN`.
+    pub fn authenticate<S: Read + Write + ?Sized>(&self, stream: &mut S) -> Result<Authenticated> {
+        stream.write_all(b"\0")?;
+
+        let mut offered: Option<Vec<Mechanism>> = None;
+        let mut tried = Vec::new();
+        let (mechanism, guid) = loop {
+            let Some(mechanism) = self
+                .mechanisms
+                .iter()
+                .copied()
+                .find(|m| !tried.contains(m) && !matches!(&offered, Some(o) if !o.contains(m)))
+            else {
+                return Err(Error::Other("authentication failed".into()));
+            };
+            tried.push(mechanism);
+
+            match mechanism {
+                Mechanism::External => match self.uid {
+                    Some(uid) => send(stream, &format!("AUTH EXTERNAL {}", hex_uid(uid)))?,
+                    None => send(stream, "AUTH EXTERNAL")?,
+                },
+                Mechanism::CookieSha1 => {
+                    let uid = self.uid.unwrap_or_else(|| ProcessEnvironment.euid());
+                    send(stream, &format!("AUTH DBUS_COOKIE_SHA1 {}", hex_uid(uid)))?
+                }
+                Mechanism::Anonymous => send(stream, "AUTH ANONYMOUS")?,
+            }
+            let mut reply = recv(stream)?;
+            while let ("DATA", data) = split(&reply) {
+                let resp = match mechanism {
+                    Mechanism::CookieSha1 => self.cookie_sha1(data),
+                    _ => Some(String::new()),
+                };
+                match resp {
+                    Some(resp) if resp.is_empty() => send(stream, "DATA")?,
+                    Some(resp) => send(stream, &format!("DATA {resp}"))?,
+                    None => send(stream, "CANCEL")?,
+                }
+                reply = recv(stream)?;
+            }
+
+            if split(&reply).0 == "ERROR" {
+                // the server answers `CANCEL` with `REJECTED`
+                send(stream, "CANCEL")?;
+                reply = recv(stream)?;
+                if split(&reply).0 != "REJECTED" {
+                    return Err(unexpected(&reply));
+                }
+            }
+
+            let (cmd, arg) = split(&reply);
+            match cmd {
+                "OK" => break (mechanism, Guid::try_from(arg)?),
+                "REJECTED" => {
+                    offered = Some(
+                        arg.split_whitespace()
+                            .filter_map(|m| m.parse().ok())
+                            .collect(),
+                    );
+                }
+                _ => return Err(unexpected(&reply)),
+            }
+        };
+
+        if let Some(expected) = &self.guid {
+            if *expected != guid {
+                return Err(Error::Other(format!(
+                    "server GUID `{guid}` doesn't match the address GUID `{expected}`"
+                )));
+            }
+        }
+
+        let mut unix_fd = false;
+        if self.unix_fd {
+            send(stream, "NEGOTIATE_UNIX_FD")?;
+            let reply = recv(stream)?;
+            match split(&reply).0 {
+                "AGREE_UNIX_FD" => unix_fd = true,
+                "ERROR" => {}
+                _ => return Err(unexpected(&reply)),
+            }
+        }
+        send(stream, "BEGIN")?;
+
+        Ok(Authenticated {
+            guid,
+            mechanism,
+            unix_fd,
+            uid: None,
+        })
+    }
+
+    // Answer the `<context> <id> <server challenge>` challenge.
+    fn cookie_sha1(&self, data: &str) -> Option<String> {
+        let data = String::from_utf8(unhex(data)?).ok()?;
+        let mut fields = data.split(' ');
+        let (Some(context), Some(id), Some(server_challenge), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return None;
+        };
+        let keyring = match &self.keyring {
+            Some(keyring) => keyring.clone(),
+            None => Keyring::new().ok()?,
+        };
+        let cookie = keyring.cookie(context, id.parse().ok()?).ok()?;
+        let client_challenge = keyring::challenge().ok()?;
+        let resp = cookie.response(server_challenge, &client_challenge);
+
+        Some(hex(format!("{client_challenge} {resp}").as_bytes()))
+    }
+}
+
+/// The server side of the handshake.
+#[derive(Debug, Clone)]
+pub struct AuthServer {
+    guid: Guid,
+    mechanisms: Vec<Mechanism>,
+    peer_uid: Option<u32>,
+    keyring: Option<Keyring>,
+    unix_fd: bool,
+}
+
+impl AuthServer {
+    /// Create a server with the given GUID, accepting `EXTERNAL`.
+    pub fn new(guid: Guid) -> Self {
+        Self {
+            guid,
+            mechanisms: vec![Mechanism::External],
+            peer_uid: None,
+            keyring: None,
+            unix_fd: false,
+        }
+    }
+
+    /// The server GUID.
+    pub fn guid(&self) -> &Guid {
+        &self.guid
+    }
+
+    /// Set the accepted mechanisms.
+    pub fn mechanisms<M: IntoIterator<Item = Mechanism>>(mut self, mechanisms: M) -> Self {
+        self.mechanisms = mechanisms.into_iter().collect();
+        self
+    }
+
+    /// Set the peer user ID, as given by the socket credentials.
+    ///
+    /// `EXTERNAL` fails without it, or if the client claims another user ID.
+    pub fn peer_uid(mut self, uid: Option<u32>) -> Self {
+        self.peer_uid = uid;
+        self
+    }
+
+    /// Set the keyring used with `DBUS_COOKIE_SHA1`, in the default context.
+    ///
+    /// `DBUS_COOKIE_SHA1` fails without it. Unlike libdbus, which reads the keyring of the user
+    /// the client claims to be, a single keyring is used: a client that can read it is accepted
+    /// whatever user ID it claims, unless the peer user ID is given.
+    pub fn keyring(mut self, keyring: Option<Keyring>) -> Self {
+        self.keyring = keyring;
+        self
+    }
+
+    /// Whether to agree to Unix file descriptor passing.
+    pub fn unix_fd(mut self, unix_fd: bool) -> Self {
+        self.unix_fd = unix_fd;
+        self
+    }
+
+    /// Run the handshake, up to and including the client `BEGIN`.
+    ///
+    /// Nothing is read past `BEGIN`, the message stream can be read from `stream` afterwards.
+    pub fn authenticate<S: Read + Write + ?Sized>(&self, stream: &mut S) -> Result<Authenticated> {
+        let mut nul = [0];
+        stream.read_exact(&mut nul)?;
+        if nul[0] != 0 {
+            return Err(Error::Other("missing initial NUL byte".into()));
+        }
+
+        let mut authenticated: Option<(Mechanism, Option<u32>)> = None;
+        let mut unix_fd = false;
+        loop {
+            let line = recv(stream)?;
+            let (cmd, arg) = split(&line);
+            match (cmd, &authenticated) {
+                ("AUTH", None) => {
+                    let (mech, resp) = split(arg);
+                    authenticated = match mech.parse() {
+                        Ok(mech) if self.mechanisms.contains(&mech) => {
+                            self.auth(stream, mech, resp)?
+                        }
+                        _ => None,
+                    };
+                    match authenticated {
+                        Some(_) => send(stream, &format!("OK {}", self.guid))?,
+                        None => self.reject(stream)?,
+                    }
+                }
+                ("CANCEL" | "ERROR", _) => {
+                    authenticated = None;
+                    self.reject(stream)?;
+                }
+                ("NEGOTIATE_UNIX_FD", Some(_)) if self.unix_fd => {
+                    unix_fd = true;
+                    send(stream, "AGREE_UNIX_FD")?;
+                }
+                ("NEGOTIATE_UNIX_FD", Some(_)) => send(
+                    stream,
+                    "ERROR \"Unix file descriptor passing is not supported\"",
+                )?,
+                ("BEGIN", Some((mechanism, uid))) => {
+                    return Ok(Authenticated {
+                        guid: self.guid.clone(),
+                        mechanism: *mechanism,
+                        unix_fd,
+                        uid: *uid,
+                    })
+                }
+                _ => send(stream, "ERROR \"Unexpected command\"")?,
+            }
+        }
+    }
+
+    // Check the credentials of a mechanism, asking for them if there is no initial response.
+    fn auth<S: Read + Write + ?Sized>(
+        &self,
+        stream: &mut S,
+        mechanism: Mechanism,
+        resp: &str,
+    ) -> Result<Option<(Mechanism, Option<u32>)>> {
+        let mut resp = resp.to_owned();
+        if resp.is_empty() && mechanism != Mechanism::Anonymous {
+            send(stream, "DATA")?;
+            let line = recv(stream)?;
+            match split(&line) {
+                ("DATA", data) => resp = data.to_owned(),
+                _ => return Ok(None),
+            }
+        }
+
+        Ok(match mechanism {
+            Mechanism::External => {
+                let Some(peer_uid) = self.peer_uid else {
+                    return Ok(None);
+                };
+                match resp.as_str() {
+                    "" => Some((mechanism, Some(peer_uid))),
+                    hex => {
+                        (unhex_uid(hex) == Some(peer_uid)).then_some((mechanism, Some(peer_uid)))
+                    }
+                }
+            }
+            Mechanism::CookieSha1 => {
+                let Some(uid) = unhex_uid(&resp) else {
+                    return Ok(None);
+                };
+                if self.peer_uid.is_some_and(|peer_uid| peer_uid != uid) {
+                    return Ok(None);
+                }
+                // the cookie only proves the client can read the keyring, not the claimed uid
+                self.cookie_sha1(stream)?
+                    .then_some((mechanism, self.peer_uid))
+            }
+            Mechanism::Anonymous => Some((mechanism, None)),
+        })
+    }
+
+    // Challenge the client with a keyring cookie.
+    fn cookie_sha1<S: Read + Write + ?Sized>(&self, stream: &mut S) -> Result<bool> {
+        let Some(keyring) = &self.keyring else {
+            return Ok(false);
+        };
+        let (Ok(cookie), Ok(server_challenge)) = (
+            keyring.current_cookie(DEFAULT_CONTEXT),
+            keyring::challenge(),
+        ) else {
+            return Ok(false);
+        };
+        let data = format!("{DEFAULT_CONTEXT} {} {server_challenge}", cookie.id());
+        send(stream, &format!("DATA {}", hex(data.as_bytes())))?;
+
+        let line = recv(stream)?;
+        let ("DATA", data) = split(&line) else {
+            return Ok(false);
+        };
+        let Some(data) = unhex(data).and_then(|d| String::from_utf8(d).ok()) else {
+            return Ok(false);
+        };
+        let Some((client_challenge, resp)) = data.split_once(' ') else {
+            return Ok(false);
+        };
+
+        let expected = cookie.response(&server_challenge, client_challenge);
+
+        Ok(constant_time_eq(resp.as_bytes(), expected.as_bytes()))
+    }
+
+    fn reject<S: Write + ?Sized>(&self, stream: &mut S) -> Result<()> {
+        let mechs: Vec<_> = self.mechanisms.iter().map(Mechanism::name).collect();
+        send(stream, &format!("REJECTED {}", mechs.join(" ")))
+    }
+}
+
+fn send<S: Write + ?Sized>(stream: &mut S, line: &str) -> Result<()> {
+    stream.write_all(format!("{line}\r\n").as_bytes())?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+// Read a `\r\n` terminated line, without reading past it.
+fn recv<S: Read + ?Sized>(stream: &mut S) -> Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    while !line.ends_with(b"\r\n") {
+        if stream.read(&mut byte)? == 0 {
+            return Err(Error::Other(
+                "connection closed during authentication".into(),
+            ));
+        }
+        line.push(byte[0]);
+        if line.len() > MAX_LINE {
+            return Err(Error::Other("authentication line is too long".into()));
+        }
+    }
+    line.truncate(line.len() - 2);
+
+    String::from_utf8(line).map_err(|e| Error::Encoding(e.to_string()))
+}
+
+// Compare without an early exit, so that the time taken doesn't tell how much of `a` is right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Split a line into its command and argument.
+fn split(line: &str) -> (&str, &str) {
+    line.split_once(' ').unwrap_or((line, ""))
+}
+
+fn unexpected(reply: &str) -> Error {
+    Error::Other(format!("unexpected authentication reply `{reply}`"))
+}
+
+// The user ID as hex-encoded ASCII decimal, such as `31303030` for 1000.
+fn hex_uid(uid: u32) -> String {
+    hex(uid.to_string().as_bytes())
+}
+
+fn unhex_uid(hex: &str) -> Option<u32> {
+    String::from_utf8(unhex(hex)?).ok()?.parse().ok()
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [hi, lo] => u8::from_str_radix(std::str::from_utf8(&[*hi, *lo]).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{os::unix::net::UnixStream, thread};
+
+    use super::*;
+
+    const GUID: &str = "9406e28972c595c590766c9564ce623f";
+
+    fn guid() -> Guid {
+        Guid::try_from(GUID).unwrap()
+    }
+
+    fn handshake(client: AuthClient, server: AuthServer) -> (Result<Authenticated>, Vec<u8>) {
+        let (mut c, mut s) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || {
+            let res = server.authenticate(&mut s);
+            // what follows BEGIN is left unread
+            let mut rest = Vec::new();
+            if res.is_ok() {
+                s.read_to_end(&mut rest).unwrap();
+            }
+            (res, rest)
+        });
+        let res = client.authenticate(&mut c);
+        if res.is_ok() {
+            c.write_all(b"hello").unwrap();
+        }
+        drop(c);
+        let (server_res, rest) = server.join().unwrap();
+        assert_eq!(res.is_ok(), server_res.is_ok());
+
+        (res.and(server_res), rest)
+    }
+
+    #[test]
+    fn constant_time() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"ab"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(hex_uid(1000), "31303030");
+        assert_eq!(unhex_uid("31303030"), Some(1000));
+        assert_eq!(unhex_uid("3130303"), None);
+        assert_eq!(unhex_uid("zz"), None);
+    }
+
+    #[test]
+    fn external() {
+        let addr = DBusAddr::try_from(format!("unix:path=/tmp/bus,guid={GUID}")).unwrap();
+        let client = AuthClient::for_address(&addr)
+            .unwrap()
+            .uid(Some(1000))
+            .negotiate_unix_fd(true);
+        let server = AuthServer::new(guid()).peer_uid(Some(1000)).unix_fd(true);
+        let (res, rest) = handshake(client, server);
+        let auth = res.unwrap();
+        assert_eq!(auth.guid(), &guid());
+        assert_eq!(auth.mechanism(), Mechanism::External);
+        assert!(auth.unix_fd());
+        assert_eq!(auth.uid(), Some(1000));
+        assert_eq!(rest, b"hello");
+
+        // without initial response, and without fd passing
+        let client = AuthClient::new().uid(None).negotiate_unix_fd(true);
+        let server = AuthServer::new(guid()).peer_uid(Some(1000));
+        let auth = handshake(client, server).0.unwrap();
+        assert!(!auth.unix_fd());
+        assert_eq!(auth.uid(), Some(1000));
+    }
+
+    #[test]
+    fn fallback() {
+        // the server doesn't accept the claimed uid, but allows anonymous clients
+        let client = AuthClient::new().uid(Some(0));
+        let server = AuthServer::new(guid())
+            .mechanisms([Mechanism::External, Mechanism::Anonymous])
+            .peer_uid(Some(1000));
+        let auth = handshake(client, server).0.unwrap();
+        assert_eq!(auth.mechanism(), Mechanism::Anonymous);
+        assert_eq!(auth.uid(), None);
+
+        let client = AuthClient::new()
+            .mechanisms([Mechanism::External])
+            .uid(Some(0));
+        let server = AuthServer::new(guid()).peer_uid(Some(1000));
+        let (mut c, mut s) = UnixStream::pair().unwrap();
+        thread::spawn(move || {
+            let _ = server.authenticate(&mut s);
+        });
+        assert_eq!(
+            client.authenticate(&mut c).unwrap_err(),
+            Error::Other("authentication failed".into())
+        );
+    }
+
+    #[test]
+    fn cookie_sha1() {
+        let tmp = crate::tests::TmpDir::new("auth-keyring");
+        let keyring = Keyring::with_dir(tmp.path().join("keyrings"));
+        let client = AuthClient::new()
+            .mechanisms([Mechanism::CookieSha1])
+            .uid(Some(1000))
+            .keyring(Some(keyring.clone()));
+        let server = AuthServer::new(guid())
+            .mechanisms([Mechanism::CookieSha1])
+            .keyring(Some(keyring.clone()));
+        let auth = handshake(client.clone(), server.clone()).0.unwrap();
+        assert_eq!(auth.mechanism(), Mechanism::CookieSha1);
+        // the claimed uid can't be checked
+        assert_eq!(auth.uid(), None);
+
+        let auth = handshake(client.clone(), server.clone().peer_uid(Some(1000)))
+            .0
+            .unwrap();
+        assert_eq!(auth.uid(), Some(1000));
+
+        // the client claims another uid than the peer one
+        let root = client.clone().uid(Some(0));
+        let (res, _) = handshake(root.clone(), server.clone().peer_uid(Some(1000)));
+        assert!(res.is_err());
+        assert_eq!(handshake(root, server).0.unwrap().uid(), None);
+
+        // the client can't read the server keyring
+        let other = Keyring::with_dir(tmp.path().join("other"));
+        let server = AuthServer::new(guid())
+            .mechanisms([Mechanism::CookieSha1])
+            .keyring(Some(keyring));
+        let (res, _) = handshake(client.keyring(Some(other)), server);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn guid_mismatch() {
+        let other = Guid::try_from("0123456789abcdef0123456789abcdef").unwrap();
+        let client = AuthClient::new().uid(Some(1000)).guid(Some(other));
+        let server = AuthServer::new(guid()).peer_uid(Some(1000));
+        let (mut c, mut s) = UnixStream::pair().unwrap();
+        thread::spawn(move || {
+            let _ = server.authenticate(&mut s);
+        });
+        assert_eq!(
+            client.authenticate(&mut c).unwrap_err(),
+            Error::Other(format!(
+                "server GUID `{GUID}` doesn't match the address GUID \
+                 `0123456789abcdef0123456789abcdef`"
+            ))
+        );
+    }
+
+    #[test]
+    fn client_error() {
+        // `CANCEL` after `ERROR` is answered with the mechanisms to try next
+        let (mut c, mut s) = UnixStream::pair().unwrap();
+        s.write_all(format!("ERROR\r\nREJECTED ANONYMOUS\r\nOK {GUID}\r\n").as_bytes())
+            .unwrap();
+        let client = AuthClient::new()
+            .mechanisms([
+                Mechanism::External,
+                Mechanism::CookieSha1,
+                Mechanism::Anonymous,
+            ])
+            .uid(Some(1000));
+        let auth = client.authenticate(&mut c).unwrap();
+        assert_eq!(auth.mechanism(), Mechanism::Anonymous);
+        drop(c);
+        let mut sent = String::new();
+        s.read_to_string(&mut sent).unwrap();
+        assert!(sent.starts_with("\0AUTH EXTERNAL 31303030\r\nCANCEL\r\nAUTH ANONYMOUS"));
+        assert!(sent.ends_with("BEGIN\r\n"));
+
+        let (mut c, mut s) = UnixStream::pair().unwrap();
+        s.write_all(b"ERROR\r\nERROR\r\n").unwrap();
+        assert_eq!(
+            AuthClient::new().authenticate(&mut c).unwrap_err(),
+            Error::Other("unexpected authentication reply `ERROR`".into())
+        );
+    }
+
+    #[test]
+    fn server_protocol() {
+        let (mut c, mut s) = UnixStream::pair().unwrap();
+        let server = thread::spawn(move || AuthServer::new(guid()).authenticate(&mut s));
+        c.write_all(b"\0AUTH\r\nBEGIN\r\nAUTH EXTERNAL 31\r\nCANCEL\r\nAUTH ANONYMOUS\r\n")
+            .unwrap();
+        c.shutdown(std::net::Shutdown::Write).unwrap();
+        assert_eq!(
+            server.join().unwrap().unwrap_err(),
+            Error::Other("connection closed during authentication".into())
+        );
+        let mut replies = String::new();
+        c.read_to_string(&mut replies).unwrap();
+        assert_eq!(
+            replies,
+            "REJECTED EXTERNAL\r\n\
+             ERROR \"Unexpected command\"\r\n\
+             REJECTED EXTERNAL\r\n\
+             REJECTED EXTERNAL\r\n\
+             REJECTED EXTERNAL\r\n"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! `dbus-addr-bridge`: connect standard I/O to a bus, like `systemd-stdio-bridge`.
+
+use std::{
+    env,
+    io::{self, Read, Write},
+    net::Shutdown,
+    process::ExitCode,
+    thread,
+};
+
+use dbus_addr::{
+    session_with, system_with, DBusAddrList, NoAutolaunch, ProbeOptions, ProcessEnvironment,
+};
+
+const USAGE: &str = "\
+Usage: dbus-addr-bridge [--address <ADDRESS> | --bus <session|system>]
+
+Copy bytes between standard I/O and a bus, until either side closes. The first working address of
+the list is used. The system bus is used by default.
+
+The command of a `unixexec:` or `x-machine-exec:` address is run with its standard I/O connected to
+the bridge. A `launchd:` address is resolved from its environment variable, and a session bus is
+only autolaunched for an `autolaunch:` address.
+";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let addr = match args.as_slice() {
+        ["-h" | "--help"] => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        ["-V" | "--version"] => {
+            println!("dbus-addr-bridge {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        [] | ["--bus", "system"] => system_with(&NoAutolaunch(ProcessEnvironment)),
+        ["--bus", "session"] => session_with(&NoAutolaunch(ProcessEnvironment)),
+        ["--address", addr] => DBusAddrList::try_from(addr.to_string()),
+        _ => {
+            eprint!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let options = ProbeOptions::new().autolaunch(true);
+    match addr.and_then(|addr| addr.connect_with(&options)) {
+        Ok(stream) => match bridge(stream) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("error: {e}");
+                ExitCode::FAILURE
+            }
+        },
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Copy standard input to the bus in a thread, and the bus to standard output. The bus is expected
+// to close the connection once it gets the end of standard input.
+fn bridge(stream: dbus_addr::DBusStream) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    thread::spawn(move || {
+        let _ = pump(io::stdin().lock(), &mut writer);
+        let _ = writer.shutdown(Shutdown::Write);
+    });
+
+    pump(stream, io::stdout().lock())
+}
+
+// Copy until the end of `r`, flushing each chunk since D-Bus messages are not line-based.
+fn pump<R: Read, W: Write>(mut r: R, mut w: W) -> io::Result<()> {
+    let mut buf = [0; 8192];
+    loop {
+        let n = match r.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        w.write_all(&buf[..n])?;
+        w.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Records the written bytes at each flush.
+    #[derive(Default)]
+    struct Flushed {
+        buf: Vec<u8>,
+        flushed: Vec<Vec<u8>>,
+    }
+
+    impl Write for Flushed {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.buf.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushed.push(self.buf.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn pump_flushes() {
+        let mut out = Flushed::default();
+        pump(&b"\0AUTH EXTERNAL"[..], &mut out).unwrap();
+        assert_eq!(out.flushed, [b"\0AUTH EXTERNAL"]);
+    }
+}
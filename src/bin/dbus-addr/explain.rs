@@ -0,0 +1,170 @@
+//! Human descriptions of addresses.
+
+use dbus_addr::{
+    transport::{AutolaunchScope, MachineRef, TcpFamily, Transport, UnixAddrKind},
+    OwnedDBusAddr,
+};
+
+/// Describe an address, such as "TCP to localhost:1234 over IPv4".
+pub fn explain(addr: &OwnedDBusAddr) -> String {
+    let mut desc = match addr.transport() {
+        Transport::Unix(u) => match u.kind() {
+            UnixAddrKind::Path(p) => format!("Unix socket at {p}"),
+            UnixAddrKind::Dir(p) => {
+                format!("Unix socket with a random name in {p} (listen only)")
+            }
+            UnixAddrKind::Tmpdir(p) => format!(
+                "Unix socket with a random name in {}, abstract if supported (listen only)",
+                p
+            ),
+            UnixAddrKind::Abstract(a) => {
+                format!("abstract Unix socket @{}", String::from_utf8_lossy(a))
+            }
+            UnixAddrKind::Runtime => {
+                "Unix socket at $XDG_RUNTIME_DIR/bus (listen only)".to_string()
+            }
+            _ => "Unix socket".to_string(),
+        },
+        Transport::Launchd(l) => format!(
+            "Unix socket from launchd, named by the {} environment variable",
+            l.env()
+        ),
+        Transport::Systemd(_) => "socket passed by systemd (listen only)".to_string(),
+        Transport::Tcp(t) => tcp("TCP", t.host(), t.bind(), t.port(), t.family()),
+        Transport::NonceTcp(t) => {
+            let mut desc = tcp(
+                "nonce-authenticated TCP",
+                t.host(),
+                t.bind(),
+                t.port(),
+                t.family(),
+            );
+            if let Some(noncefile) = t.noncefile() {
+                desc.push_str(&format!(", nonce in {noncefile}"));
+            }
+            desc
+        }
+        Transport::Unixexec(e) => exec(e.path(), e.argv()),
+        Transport::MachineUnix(m) => format!(
+            "Unix socket at {} in {}",
+            m.socket_path(),
+            machine(m.machine())
+        ),
+        Transport::MachineExec(m) => {
+            format!("{} in {}", exec(m.path(), m.argv()), machine(m.machine()))
+        }
+        Transport::Autolaunch(a) => match a.scope() {
+            Some(AutolaunchScope::InstallPath) => {
+                "autolaunched bus, one per installation path".to_string()
+            }
+            Some(AutolaunchScope::User) => "autolaunched bus, one per user".to_string(),
+            Some(scope) => format!("autolaunched bus, in scope `{scope}`"),
+            None => "autolaunched bus".to_string(),
+        },
+        Transport::Vsock(v) => format!(
+            "VSOCK to CID {} port {}",
+            v.cid().map_or("any".to_string(), |c| c.to_string()),
+            v.port().map_or("any".to_string(), |p| p.to_string()),
+        ),
+        other => format!("unknown `{other}` transport"),
+    };
+    if let Some(guid) = addr.guid() {
+        desc.push_str(&format!(", server GUID {guid}"));
+    }
+
+    desc
+}
+
+fn exec(path: &str, argv: &[(usize, std::borrow::Cow<'_, str>)]) -> String {
+    let mut desc = format!("standard I/O of `{path}`");
+    if !argv.is_empty() {
+        let argv: Vec<String> = argv.iter().map(|(n, a)| format!("{n}={a}")).collect();
+        desc.push_str(&format!(" with arguments {}", argv.join(" ")));
+    }
+
+    desc
+}
+
+fn machine(machine: &MachineRef<'_>) -> String {
+    match machine {
+        MachineRef::Name(name) if name == ".host" => "the host".to_string(),
+        MachineRef::Name(name) => format!("machine `{name}`"),
+        MachineRef::Pid(pid) => format!("the machine of PID {pid}"),
+        _ => "a machine".to_string(),
+    }
+}
+
+fn tcp(
+    what: &str,
+    host: Option<&str>,
+    bind: Option<&str>,
+    port: Option<u16>,
+    family: Option<TcpFamily>,
+) -> String {
+    let mut desc = format!("{what} to {}", host.unwrap_or("localhost"));
+    match port {
+        Some(0) => desc.push_str(", any port"),
+        Some(port) => desc.push_str(&format!(":{port}")),
+        None => desc.push_str(", no port"),
+    }
+    match family {
+        Some(TcpFamily::IPv4) => desc.push_str(" over IPv4"),
+        Some(TcpFamily::IPv6) => desc.push_str(" over IPv6"),
+        _ => {}
+    }
+    if let Some(bind) = bind {
+        desc.push_str(&format!(", listening on {bind}"));
+    }
+
+    desc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptions() {
+        for (addr, desc) in [
+            (
+                "tcp:host=localhost,port=1234,family=ipv4",
+                "TCP to localhost:1234 over IPv4",
+            ),
+            (
+                "unix:path=/run/dbus/system_bus_socket,guid=9406e28972c595c590766c9564ce623f",
+                "Unix socket at /run/dbus/system_bus_socket, server GUID \
+                 9406e28972c595c590766c9564ce623f",
+            ),
+            (
+                "unix:abstract=/tmp/dbus-x",
+                "abstract Unix socket @/tmp/dbus-x",
+            ),
+            (
+                "nonce-tcp:port=0,noncefile=/tmp/nonce",
+                "nonce-authenticated TCP to localhost, any port, nonce in /tmp/nonce",
+            ),
+            (
+                "unixexec:path=ssh,argv1=host,argv2=dbus-stdio",
+                "standard I/O of `ssh` with arguments 1=host 2=dbus-stdio",
+            ),
+            ("autolaunch:scope=*user", "autolaunched bus, one per user"),
+            ("vsock:cid=2", "VSOCK to CID 2 port any"),
+            (
+                "x-machine-unix:machine=foo",
+                "Unix socket at /run/dbus/system_bus_socket in machine `foo`",
+            ),
+            (
+                "x-machine-unix:pid=42,path=/run/bus",
+                "Unix socket at /run/bus in the machine of PID 42",
+            ),
+            (
+                "x-machine-exec:machine=.host,path=systemd-stdio-bridge,argv1=--user",
+                "standard I/O of `systemd-stdio-bridge` with arguments 1=--user in the host",
+            ),
+            ("foo:", "unknown `foo` transport"),
+        ] {
+            let addr = OwnedDBusAddr::try_from(addr).unwrap();
+            assert_eq!(explain(&addr), desc);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! JSON output.
+
+use std::fmt::Write;
+
+use dbus_addr::{
+    decode_percents, encode_percents,
+    transport::{MachineRef, Transport, UnixAddrKind},
+    OwnedDBusAddr,
+};
+
+use super::Entry;
+
+enum Value {
+    Str(String),
+    Num(u64),
+    Bool(bool),
+    Null,
+}
+
+impl Value {
+    fn opt_str<S: ToString>(s: Option<S>) -> Self {
+        s.map_or(Value::Null, |s| Value::Str(s.to_string()))
+    }
+
+    fn opt_num<N: Into<u64>>(n: Option<N>) -> Self {
+        n.map_or(Value::Null, |n| Value::Num(n.into()))
+    }
+
+    // Bytes that may not be valid UTF-8, percent-encoded as in addresses.
+    fn bytes(b: &[u8]) -> Self {
+        let mut s = String::new();
+        encode_percents(&mut s, b).unwrap();
+        Value::Str(s)
+    }
+}
+
+/// The address list as a JSON array, with an object per address.
+///
+/// Each object has the `transport` name, the `guid`, and the transport keys. Abstract socket names
+/// and the values of unknown transports are bytes, so they are percent-encoded as in the address.
+pub fn to_json(addrs: &[Entry<'_>]) -> String {
+    let mut out = String::from("[");
+    for (i, (entry, addr)) in addrs.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("\n  {");
+        for (j, (key, val)) in fields(entry, addr).iter().enumerate() {
+            if j > 0 {
+                out.push_str(", ");
+            }
+            write_str(&mut out, key);
+            out.push_str(": ");
+            match val {
+                Value::Str(s) => write_str(&mut out, s),
+                Value::Num(n) => write!(out, "{n}").unwrap(),
+                Value::Bool(b) => write!(out, "{b}").unwrap(),
+                Value::Null => out.push_str("null"),
+            }
+        }
+        out.push('}');
+    }
+    out.push_str("\n]");
+
+    out
+}
+
+fn fields(entry: &str, addr: &OwnedDBusAddr) -> Vec<(String, Value)> {
+    let mut fields = vec![
+        ("transport".into(), Value::Str(addr.transport().to_string())),
+        ("guid".into(), Value::opt_str(addr.guid())),
+    ];
+
+    match addr.transport() {
+        Transport::Unix(u) => fields.push(match u.kind() {
+            UnixAddrKind::Path(p) => ("path".into(), Value::Str(p.to_string())),
+            UnixAddrKind::Dir(p) => ("dir".into(), Value::Str(p.to_string())),
+            UnixAddrKind::Tmpdir(p) => ("tmpdir".into(), Value::Str(p.to_string())),
+            UnixAddrKind::Abstract(a) => ("abstract".into(), Value::bytes(a)),
+            _ => ("runtime".into(), Value::Bool(true)),
+        }),
+        Transport::Launchd(l) => fields.push(("env".into(), Value::Str(l.env().into()))),
+        Transport::Tcp(t) => fields.extend([
+            ("host".into(), Value::opt_str(t.host())),
+            ("bind".into(), Value::opt_str(t.bind())),
+            ("port".into(), Value::opt_num(t.port())),
+            ("family".into(), Value::opt_str(t.family())),
+        ]),
+        Transport::NonceTcp(t) => fields.extend([
+            ("host".into(), Value::opt_str(t.host())),
+            ("bind".into(), Value::opt_str(t.bind())),
+            ("port".into(), Value::opt_num(t.port())),
+            ("family".into(), Value::opt_str(t.family())),
+            ("noncefile".into(), Value::opt_str(t.noncefile())),
+        ]),
+        Transport::Unixexec(e) => {
+            fields.push(("path".into(), Value::Str(e.path().to_string())));
+            for (n, arg) in e.argv() {
+                fields.push((format!("argv{n}"), Value::Str(arg.to_string())));
+            }
+        }
+        Transport::Autolaunch(a) => fields.push(("scope".into(), Value::opt_str(a.scope()))),
+        Transport::Vsock(v) => fields.extend([
+            ("cid".into(), Value::opt_num(v.cid())),
+            ("port".into(), Value::opt_num(v.port())),
+        ]),
+        Transport::MachineUnix(m) => {
+            fields.push(machine(m.machine()));
+            fields.push(("path".into(), Value::opt_str(m.path())));
+        }
+        Transport::MachineExec(m) => {
+            fields.push(machine(m.machine()));
+            fields.push(("path".into(), Value::Str(m.path().to_string())));
+            for (n, arg) in m.argv() {
+                fields.push((format!("argv{n}"), Value::Str(arg.to_string())));
+            }
+        }
+        // the parsed address only keeps the name, the keys are in the entry
+        Transport::Other(_) => fields.extend(other_keys(entry)),
+        _ => {}
+    }
+
+    fields
+}
+
+// The keys of an address entry of an unknown transport, except `guid`.
+fn other_keys(entry: &str) -> Vec<(String, Value)> {
+    let Some((_, keys)) = entry.split_once(':') else {
+        return vec![];
+    };
+
+    keys.split(',')
+        .filter(|pair| !pair.is_empty())
+        .filter_map(|pair| match pair.split_once('=') {
+            Some(("guid", _)) => None,
+            Some((k, v)) => {
+                // like the parser, ignore anything after a second `=`, the rest decodes since
+                // the entry is valid
+                let v = v.split('=').next().unwrap_or_default();
+                Some((k.into(), Value::bytes(&decode_percents(v).unwrap())))
+            }
+            None => Some((pair.into(), Value::Null)),
+        })
+        .collect()
+}
+
+fn machine(machine: &MachineRef<'_>) -> (String, Value) {
+    match machine {
+        MachineRef::Name(name) => ("machine".into(), Value::Str(name.to_string())),
+        MachineRef::Pid(pid) => ("pid".into(), Value::Num((*pid).into())),
+        _ => ("machine".into(), Value::Null),
+    }
+}
+
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c < ' ' => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json() {
+        let addrs = [
+            "tcp:host=localhost,port=1234",
+            "unixexec:path=/bin/sh,argv1=%22%0A",
+            "x-machine-unix:pid=42",
+            "unix:abstract=%00dbus%FF%22",
+            "foo:bar=%FF%20x,baz,guid=0123456789abcdef0123456789abcdef",
+        ]
+        .map(|entry| (entry, OwnedDBusAddr::try_from(entry).unwrap()));
+        assert_eq!(
+            to_json(&addrs),
+            r#"[
+  {"transport": "tcp", "guid": null, "host": "localhost", "bind": null, "port": 1234, "family": null},
+  {"transport": "unixexec", "guid": null, "path": "/bin/sh", "argv1": "\"\n"},
+  {"transport": "x-machine-unix", "guid": null, "pid": 42, "path": null},
+  {"transport": "unix", "guid": null, "abstract": "%00dbus%FF%22"},
+  {"transport": "foo", "guid": "0123456789abcdef0123456789abcdef", "bar": "%FF%20x", "baz": null}
+]"#
+        );
+    }
+}
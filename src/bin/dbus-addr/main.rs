@@ -0,0 +1,313 @@
+//! `dbus-addr`: check and inspect D-Bus addresses.
+
+use std::{
+    env,
+    io::{self, Read},
+    ops::Range,
+    process::ExitCode,
+    time::Duration,
+};
+
+use dbus_addr::{
+    decode_percents, discover_session_with, discover_system_with, DBusAddrList, Error,
+    NoAutolaunch, OwnedDBusAddr, ProbeOptions, ProcessEnvironment, RootDir,
+};
+
+mod explain;
+mod json;
+
+const USAGE: &str = "\
+Usage: dbus-addr <COMMAND> [ADDRESS]
+       dbus-addr probe [--timeout <MS>] [--no-auth] [--root <DIR>] [ADDRESS]
+
+Commands:
+  parse      Parse the address list and print its entries
+  validate   Check the address list, printing nothing if it is valid
+  normalize  Print the address list in canonical form
+  explain    Describe each address of the list
+  json       Print the address list as JSON
+  probe      Check that a D-Bus server answers at each address of the list
+  session    Print the session bus address and where it was found, without autolaunching it
+  system     Print the system bus address and where it was found
+
+The address list is read from standard input if ADDRESS is not given, or is `-`.
+";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["-h" | "--help" | "help"] => {
+            print!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        ["-V" | "--version"] => {
+            println!("dbus-addr {}", env!("CARGO_PKG_VERSION"));
+            ExitCode::SUCCESS
+        }
+        ["probe", args @ ..] => probe(args),
+        // an inspection command must not start a bus
+        ["session"] => discovered(
+            "session",
+            discover_session_with(&NoAutolaunch(ProcessEnvironment)),
+        ),
+        ["system"] => discovered(
+            "system",
+            discover_system_with(&NoAutolaunch(ProcessEnvironment)),
+        ),
+        [cmd @ ("parse" | "validate" | "normalize" | "explain" | "json")]
+        | [cmd @ ("parse" | "validate" | "normalize" | "explain" | "json"), "-"] => run_stdin(cmd),
+        [cmd @ ("parse" | "validate" | "normalize" | "explain" | "json"), addr] => run(cmd, addr),
+        _ => {
+            eprint!("{USAGE}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run_stdin(cmd: &str) -> ExitCode {
+    match read_stdin() {
+        Some(input) => run(cmd, &input),
+        None => ExitCode::FAILURE,
+    }
+}
+
+fn read_stdin() -> Option<String> {
+    let mut input = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut input) {
+        eprintln!("error: failed to read standard input: {e}");
+        return None;
+    }
+    input.truncate(input.trim_end_matches(['\r', '\n']).len());
+
+    Some(input)
+}
+
+fn run(cmd: &str, input: &str) -> ExitCode {
+    let addrs = match parse(input) {
+        Ok(addrs) => addrs,
+        Err((span, e)) => {
+            eprint!("{}", report(input, span, &e));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match cmd {
+        "parse" => {
+            for (_, addr) in &addrs {
+                println!("{addr:#?}");
+            }
+        }
+        "normalize" => {
+            let addrs: Vec<String> = addrs.iter().map(|(_, addr)| addr.to_string()).collect();
+            println!("{}", addrs.join(";"));
+        }
+        "explain" => {
+            for (i, (_, addr)) in addrs.iter().enumerate() {
+                println!("{}: {}", i + 1, explain::explain(addr));
+            }
+        }
+        "json" => println!("{}", json::to_json(&addrs)),
+        _ => {}
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn probe(mut args: &[&str]) -> ExitCode {
+    let mut options = ProbeOptions::new();
+    let input = loop {
+        match args {
+            ["--no-auth", rest @ ..] => {
+                options = options.auth(false);
+                args = rest;
+            }
+            ["--root", dir, rest @ ..] => {
+                options = options.root(RootDir::new(dir));
+                args = rest;
+            }
+            ["--timeout", ms, rest @ ..] => {
+                let Ok(ms) = ms.parse() else {
+                    eprintln!("error: invalid timeout `{ms}`");
+                    return ExitCode::from(2);
+                };
+                options = options.timeout(Duration::from_millis(ms));
+                args = rest;
+            }
+            [] | ["-"] => match read_stdin() {
+                Some(input) => break input,
+                None => return ExitCode::FAILURE,
+            },
+            [addr] => break addr.to_string(),
+            _ => {
+                eprint!("{USAGE}");
+                return ExitCode::from(2);
+            }
+        }
+    };
+
+    let list = match DBusAddrList::try_from(input.as_str()) {
+        Ok(list) => list,
+        Err(e) => {
+            eprint!("{}", report(&input, 0..input.len(), &e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let reports = list.probe_with(&options);
+    for (i, report) in reports.iter().enumerate() {
+        println!("{}: {}", i + 1, report.address());
+        if let Some(endpoint) = report.endpoint() {
+            println!("   endpoint: {endpoint}");
+        }
+        match (report.latency(), report.error()) {
+            (_, Some(e)) => println!("   error: {e}"),
+            (Some(latency), None) => match report.mechanisms() {
+                Some(mechs) => println!("   ok in {latency:.1?}, mechanisms: {}", mechs.join(" ")),
+                None => println!("   ok in {latency:.1?}"),
+            },
+            (None, None) => {}
+        }
+    }
+    if reports.is_empty() {
+        eprintln!("error: {}", Error::MissingTransport);
+    }
+
+    if reports.iter().any(|r| r.is_ok()) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn discovered(bus: &str, discovery: dbus_addr::Result<dbus_addr::Discovery>) -> ExitCode {
+    match discovery {
+        Ok(d) => {
+            println!("{}", d.address());
+            println!("source: {}", d.source());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: no {bus} bus: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// An entry of the address list, and the parsed address.
+type Entry<'a> = (&'a str, OwnedDBusAddr);
+
+// Parse every address of the list, or return the first error and its location in `list`.
+fn parse(list: &str) -> Result<Vec<Entry<'_>>, (Range<usize>, Error)> {
+    let mut addrs = Vec::new();
+    let mut start = 0;
+    // like the library list iterator, a trailing `;` is ignored
+    while start < list.len() {
+        let end = list[start..].find(';').map_or(list.len(), |i| start + i);
+        let entry = &list[start..end];
+        match OwnedDBusAddr::try_from(entry) {
+            Ok(addr) => addrs.push((entry, addr)),
+            Err(e) => {
+                let span = entry_span(entry, &e);
+                return Err((start + span.start..start + span.end, e));
+            }
+        }
+        start = end + 1;
+    }
+    if addrs.is_empty() {
+        return Err((0..list.len(), Error::MissingTransport));
+    }
+
+    Ok(addrs)
+}
+
+// The part of an address entry an error is about: the offending `key=value` pair if known, or the
+// whole entry.
+fn entry_span(entry: &str, err: &Error) -> Range<usize> {
+    let whole = 0..entry.len();
+    let key = match err {
+        Error::DuplicateKey(k) | Error::MissingValue(k) | Error::InvalidValue(k) => {
+            Some(k.as_str())
+        }
+        Error::UnknownTcpFamily(_) => Some("family"),
+        Error::Encoding(_) => None,
+        _ => return whole,
+    };
+    let Some(col) = entry.find(':') else {
+        return whole;
+    };
+
+    let mut offset = col + 1;
+    for pair in entry[col + 1..].split(',') {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        match key {
+            Some(key) if k == key => return offset..offset + pair.len(),
+            None if !decodes(v) => return offset + k.len() + 1..offset + pair.len(),
+            _ => {}
+        }
+        offset += pair.len() + 1;
+    }
+
+    whole
+}
+
+fn decodes(value: &str) -> bool {
+    decode_percents(value).is_ok_and(|v| std::str::from_utf8(&v).is_ok())
+}
+
+// An error message, with the input and the offending part underlined.
+fn report(input: &str, span: Range<usize>, err: &Error) -> String {
+    let pad = input[..span.start].chars().count();
+    let len = input[span].chars().count().max(1);
+
+    format!(
+        "error: {err}\n  |\n  | {input}\n  | {}{}\n",
+        " ".repeat(pad),
+        "^".repeat(len)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans() {
+        let list = "unix:path=/tmp/a;tcp:host=localhost,port=abc";
+        let (span, e) = parse(list).unwrap_err();
+        assert_eq!(&list[span], "port=abc");
+        assert_eq!(e, Error::InvalidValue("port".into()));
+
+        let list = "tcp:family=ipv5";
+        let (span, _) = parse(list).unwrap_err();
+        assert_eq!(&list[span], "family=ipv5");
+
+        let list = "unix:path=/tmp/%zz";
+        let (span, _) = parse(list).unwrap_err();
+        assert_eq!(&list[span], "/tmp/%zz");
+
+        let list = "unix:path=/tmp/a;launchd:";
+        let (span, _) = parse(list).unwrap_err();
+        assert_eq!(&list[span], "launchd:");
+
+        let (span, e) = parse("").unwrap_err();
+        assert_eq!((span, e), (0..0, Error::MissingTransport));
+
+        assert_eq!(parse("unix:path=/tmp/a;").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn reports() {
+        let list = "tcp:host=localhost,port=abc";
+        let (span, e) = parse(list).unwrap_err();
+        assert_eq!(
+            report(list, span, &e),
+            "\
+error: Invalid value for key: `port`
+  |
+  | tcp:host=localhost,port=abc
+  |                    ^^^^^^^^
+"
+        );
+    }
+}
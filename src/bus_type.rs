@@ -0,0 +1,175 @@
+//! Well-known bus types.
+
+use alloc::borrow::ToOwned;
+use core::{fmt, str::FromStr};
+
+#[cfg(feature = "std")]
+use super::{
+    discover::{session_with, starter_with, system_with},
+    Environment, ProcessEnvironment,
+};
+use super::{DBusAddrList, Error, Result};
+
+/// A bus, either well-known or given by address.
+///
+/// Example:
+/// ```
+/// use dbus_addr::BusType;
+///
+/// let bus: BusType = "system".parse().unwrap();
+/// assert_eq!(bus, BusType::System);
+///
+/// let bus: BusType = "unix:path=/tmp/bus".parse().unwrap();
+/// assert_eq!(bus.to_string(), "unix:path=/tmp/bus");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BusType {
+    /// The session bus.
+    Session,
+    /// The system bus.
+    System,
+    /// The bus that started this process.
+    Starter,
+    /// A bus given by address.
+    Address(DBusAddrList<'static>),
+}
+
+impl BusType {
+    /// The bus address list, as discovered in the current process environment.
+    #[cfg(feature = "std")]
+    pub fn address(&self) -> Result<DBusAddrList<'static>> {
+        self.address_with(&ProcessEnvironment)
+    }
+
+    /// The bus address list, as discovered in the given environment.
+    #[cfg(feature = "std")]
+    pub fn address_with<E: Environment + ?Sized>(&self, env: &E) -> Result<DBusAddrList<'static>> {
+        match self {
+            Self::Session => session_with(env),
+            Self::System => system_with(env),
+            Self::Starter => starter_with(env),
+            Self::Address(addr) => Ok(addr.clone()),
+        }
+    }
+
+    /// Convert a DBUS_STARTER_BUS_TYPE value.
+    ///
+    /// Only `session` and `system` are valid starter bus types.
+    pub fn from_starter_bus_type(s: &str) -> Result<Self> {
+        match s {
+            "session" => Ok(Self::Session),
+            "system" => Ok(Self::System),
+            _ => Err(Error::InvalidValue("DBUS_STARTER_BUS_TYPE".into())),
+        }
+    }
+
+    /// The DBUS_STARTER_BUS_TYPE value, for the session and system buses.
+    pub fn starter_bus_type(&self) -> Option<&'static str> {
+        match self {
+            Self::Session => Some("session"),
+            Self::System => Some("system"),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BusType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Session => write!(f, "session"),
+            Self::System => write!(f, "system"),
+            Self::Starter => write!(f, "starter"),
+            Self::Address(addr) => write!(f, "{addr}"),
+        }
+    }
+}
+
+impl FromStr for BusType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "session" => Ok(Self::Session),
+            "system" => Ok(Self::System),
+            "starter" => Ok(Self::Starter),
+            _ => {
+                let addr = DBusAddrList::try_from(s.to_owned())?;
+                addr.validate()?;
+
+                Ok(Self::Address(addr))
+            }
+        }
+    }
+}
+
+impl From<DBusAddrList<'static>> for BusType {
+    fn from(addr: DBusAddrList<'static>) -> Self {
+        Self::Address(addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        for s in [
+            "session",
+            "system",
+            "starter",
+            "unix:path=/tmp/a;tcp:port=1",
+        ] {
+            let bus: BusType = s.parse().unwrap();
+            assert_eq!(bus.to_string(), s);
+        }
+
+        assert_eq!("".parse::<BusType>().unwrap_err(), Error::MissingTransport);
+        assert_eq!(
+            "sesion".parse::<BusType>().unwrap_err(),
+            Error::MissingTransport
+        );
+        assert_eq!(
+            "unix:path=/tmp/a;unixexec:".parse::<BusType>().unwrap_err(),
+            Error::MissingKey("path".into())
+        );
+    }
+
+    #[test]
+    fn starter_bus_type() {
+        assert_eq!(
+            BusType::from_starter_bus_type("session").unwrap(),
+            BusType::Session
+        );
+        assert_eq!(
+            BusType::from_starter_bus_type("system").unwrap(),
+            BusType::System
+        );
+        assert!(BusType::from_starter_bus_type("starter").is_err());
+        assert_eq!(BusType::System.starter_bus_type(), Some("system"));
+        assert_eq!(BusType::Starter.starter_bus_type(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn address() {
+        let env = crate::MapEnvironment::new()
+            .with_var("DBUS_SESSION_BUS_ADDRESS", "unix:path=/tmp/session")
+            .with_var("DBUS_SYSTEM_BUS_ADDRESS", "unix:path=/tmp/system")
+            .with_var("DBUS_STARTER_BUS_TYPE", "system");
+        assert_eq!(
+            BusType::Session.address_with(&env).unwrap().to_string(),
+            "unix:path=/tmp/session"
+        );
+        assert_eq!(
+            BusType::System.address_with(&env).unwrap().to_string(),
+            "unix:path=/tmp/system"
+        );
+        assert_eq!(
+            BusType::Starter.address_with(&env).unwrap().to_string(),
+            "unix:path=/tmp/system"
+        );
+        let bus = BusType::from(DBusAddrList::try_from("tcp:port=1").unwrap());
+        assert_eq!(bus.address_with(&env).unwrap().to_string(), "tcp:port=1");
+    }
+}
@@ -0,0 +1,214 @@
+//! Bus addresses of child processes.
+
+use super::{BusType, Error, Result, ToOwnedDBusAddrs};
+
+const SESSION: &str = "DBUS_SESSION_BUS_ADDRESS";
+const SYSTEM: &str = "DBUS_SYSTEM_BUS_ADDRESS";
+const STARTER_ADDRESS: &str = "DBUS_STARTER_ADDRESS";
+const STARTER_BUS_TYPE: &str = "DBUS_STARTER_BUS_TYPE";
+
+/// Set the bus addresses in the environment of a child process.
+///
+/// This is implemented for [`std::process::Command`], and for `tokio::process::Command` with the
+/// `tokio` feature. Addresses can be given as a [`crate::DBusAddrList`], an
+/// [`crate::OwnedDBusAddr`] or a string. Listen-only addresses, such as `unix:tmpdir=/tmp`, are
+/// refused.
+///
+/// Example:
+/// ```no_run
+/// use std::process::Command;
+///
+/// use dbus_addr::{DBusAddrList, DBusCommandExt};
+///
+/// let addr = DBusAddrList::try_from("unix:path=/run/user/1000/bus").unwrap();
+/// Command::new("gdbus")
+///     .dbus_env_clear()
+///     .dbus_session(&addr)
+///     .unwrap()
+///     .args(["call", "--session", "--dest", "org.freedesktop.DBus"])
+///     .spawn()
+///     .unwrap();
+/// ```
+pub trait DBusCommandExt {
+    /// Set `DBUS_SESSION_BUS_ADDRESS`.
+    fn dbus_session<'a, A: ToOwnedDBusAddrs<'a> + ?Sized>(
+        &mut self,
+        addr: &'a A,
+    ) -> Result<&mut Self>;
+
+    /// Set `DBUS_SYSTEM_BUS_ADDRESS`.
+    fn dbus_system<'a, A: ToOwnedDBusAddrs<'a> + ?Sized>(
+        &mut self,
+        addr: &'a A,
+    ) -> Result<&mut Self>;
+
+    /// Set `DBUS_STARTER_ADDRESS` and `DBUS_STARTER_BUS_TYPE`, as an activated service expects.
+    ///
+    /// The bus type must be [`BusType::Session`] or [`BusType::System`].
+    fn dbus_starter<'a, A: ToOwnedDBusAddrs<'a> + ?Sized>(
+        &mut self,
+        bus_type: &BusType,
+        addr: &'a A,
+    ) -> Result<&mut Self>;
+
+    /// Remove the inherited bus addresses from the environment.
+    fn dbus_env_clear(&mut self) -> &mut Self;
+}
+
+macro_rules! impl_command_ext {
+    ($command:ty) => {
+        impl DBusCommandExt for $command {
+            fn dbus_session<'a, A: ToOwnedDBusAddrs<'a> + ?Sized>(
+                &mut self,
+                addr: &'a A,
+            ) -> Result<&mut Self> {
+                Ok(self.env(SESSION, env_value(addr)?))
+            }
+
+            fn dbus_system<'a, A: ToOwnedDBusAddrs<'a> + ?Sized>(
+                &mut self,
+                addr: &'a A,
+            ) -> Result<&mut Self> {
+                Ok(self.env(SYSTEM, env_value(addr)?))
+            }
+
+            fn dbus_starter<'a, A: ToOwnedDBusAddrs<'a> + ?Sized>(
+                &mut self,
+                bus_type: &BusType,
+                addr: &'a A,
+            ) -> Result<&mut Self> {
+                let bus_type = bus_type
+                    .starter_bus_type()
+                    .ok_or_else(|| Error::InvalidValue(STARTER_BUS_TYPE.into()))?;
+
+                Ok(self
+                    .env(STARTER_ADDRESS, env_value(addr)?)
+                    .env(STARTER_BUS_TYPE, bus_type))
+            }
+
+            fn dbus_env_clear(&mut self) -> &mut Self {
+                self.env_remove(SESSION)
+                    .env_remove(SYSTEM)
+                    .env_remove(STARTER_ADDRESS)
+                    .env_remove(STARTER_BUS_TYPE)
+            }
+        }
+    };
+}
+
+impl_command_ext!(std::process::Command);
+
+#[cfg(feature = "tokio")]
+impl_command_ext!(tokio::process::Command);
+
+// The `;`-separated list of connectable addresses.
+fn env_value<'a, A: ToOwnedDBusAddrs<'a> + ?Sized>(addr: &'a A) -> Result<String> {
+    let mut value = String::new();
+    for addr in addr.to_owned_dbus_addrs() {
+        let addr = addr?;
+        if addr.transport().is_listen_only() {
+            return Err(Error::Other(format!("`{addr}` is a listen-only address")));
+        }
+        if !value.is_empty() {
+            value.push(';');
+        }
+        value.push_str(&addr.to_string());
+    }
+    if value.is_empty() {
+        return Err(Error::MissingTransport);
+    }
+
+    Ok(value)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{collections::HashMap, ffi::OsStr, process::Command};
+
+    use super::*;
+    use crate::{DBusAddrList, OwnedDBusAddr};
+
+    fn envs(cmd: &Command) -> HashMap<&str, Option<&str>> {
+        cmd.get_envs()
+            .map(|(k, v)| (k.to_str().unwrap(), v.and_then(OsStr::to_str)))
+            .collect()
+    }
+
+    #[test]
+    fn envs_set() {
+        let session =
+            DBusAddrList::try_from("unix:path=/tmp/session;tcp:host=localhost,port=1").unwrap();
+        let system = OwnedDBusAddr::try_from("unix:path=/run/dbus/system_bus_socket").unwrap();
+        let mut cmd = Command::new("true");
+        cmd.dbus_env_clear()
+            .dbus_session(&session)
+            .unwrap()
+            .dbus_system(&system)
+            .unwrap()
+            .dbus_starter(&BusType::System, &system)
+            .unwrap();
+        assert_eq!(
+            envs(&cmd),
+            HashMap::from([
+                (
+                    SESSION,
+                    Some("unix:path=/tmp/session;tcp:host=localhost,port=1")
+                ),
+                (SYSTEM, Some("unix:path=/run/dbus/system_bus_socket")),
+                (
+                    STARTER_ADDRESS,
+                    Some("unix:path=/run/dbus/system_bus_socket")
+                ),
+                (STARTER_BUS_TYPE, Some("system")),
+            ])
+        );
+
+        let mut cmd = Command::new("true");
+        cmd.dbus_env_clear();
+        assert_eq!(
+            envs(&cmd),
+            HashMap::from([
+                (SESSION, None),
+                (SYSTEM, None),
+                (STARTER_ADDRESS, None),
+                (STARTER_BUS_TYPE, None),
+            ])
+        );
+        let out = Command::new("sh")
+            .env(SESSION, "inherited")
+            .dbus_env_clear()
+            .args(["-c", "echo ${DBUS_SESSION_BUS_ADDRESS-unset}"])
+            .output()
+            .unwrap();
+        assert_eq!(out.stdout, b"unset\n");
+    }
+
+    #[test]
+    fn refused() {
+        let mut cmd = Command::new("true");
+        assert_eq!(
+            cmd.dbus_session(
+                &DBusAddrList::try_from("unix:path=/tmp/bus;unix:tmpdir=/tmp").unwrap()
+            )
+            .unwrap_err(),
+            Error::Other("`unix:tmpdir=/tmp` is a listen-only address".into())
+        );
+        assert_eq!(
+            cmd.dbus_starter(&BusType::Starter, "unix:path=/tmp/bus")
+                .unwrap_err(),
+            Error::InvalidValue(STARTER_BUS_TYPE.into())
+        );
+        assert!(cmd.dbus_system("tcp:port=abc").is_err());
+        assert_eq!(cmd.get_envs().count(), 0);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn tokio() {
+        let addr = OwnedDBusAddr::try_from("unix:path=/tmp/bus").unwrap();
+        let mut cmd = tokio::process::Command::new("true");
+        cmd.dbus_env_clear().dbus_session(&addr).unwrap();
+        assert_eq!(envs(cmd.as_std())[SESSION], Some("unix:path=/tmp/bus"));
+        assert_eq!(envs(cmd.as_std())[SYSTEM], None);
+    }
+}
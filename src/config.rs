@@ -0,0 +1,686 @@
+//! dbus-daemon configuration files.
+//!
+//! Reads `session.conf`/`system.conf`-style files, following `<include>` and `<includedir>`, to
+//! collect the `<listen>` addresses and the `<auth>` mechanisms. Only the top-level elements of
+//! `<busconfig>` are considered, policies are ignored.
+//!
+//! <https://dbus.freedesktop.org/doc/dbus-daemon.1.html#configuration_file>
+//!
+//! Example:
+//! ```no_run
+//! use dbus_addr::config::BusConfig;
+//!
+//! let config = BusConfig::load("/usr/share/dbus-1/system.conf").unwrap();
+//! for listen in config.listens() {
+//!     if let Err(e) = listen.parse() {
+//!         eprintln!("{e}");
+//!     }
+//! }
+//! for listen in config.unprotected_tcp() {
+//!     eprintln!("{}: TCP listener without authentication", listen.location());
+//! }
+//! ```
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{transport::Transport, DBusAddr, Error};
+
+// Deeper nesting is most likely an include loop.
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// A position in a configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    file: PathBuf,
+    line: usize,
+}
+
+impl Location {
+    /// The configuration file.
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// The line, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file.display(), self.line)
+    }
+}
+
+/// An error in a configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    location: Option<Location>,
+    error: Error,
+}
+
+impl ConfigError {
+    /// Where the error is, if known.
+    pub fn location(&self) -> Option<&Location> {
+        self.location.as_ref()
+    }
+
+    /// The underlying error.
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    fn at(location: &Location, error: Error) -> Self {
+        Self {
+            location: Some(location.clone()),
+            error,
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{location}: {}", self.error),
+            None => self.error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// A `<listen>` element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Listen {
+    address: String,
+    location: Location,
+}
+
+impl Listen {
+    /// The address, as written.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Where the element is.
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+
+    /// Parse and validate the address.
+    pub fn parse(&self) -> Result<DBusAddr<'_>, ConfigError> {
+        let addr = DBusAddr::try_from(self.address.as_str())
+            .map_err(|e| ConfigError::at(&self.location, e))?;
+        addr.transport()
+            .and(addr.guid())
+            .map_err(|e| ConfigError::at(&self.location, e))?;
+
+        Ok(addr)
+    }
+}
+
+/// The addresses and authentication settings of a bus configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BusConfig {
+    files: Vec<PathBuf>,
+    listens: Vec<Listen>,
+    auth: Vec<String>,
+    allow_anonymous: bool,
+}
+
+impl BusConfig {
+    /// Load a configuration file and its includes.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+        config.load_file(path.as_ref(), None, &mut vec![])?;
+
+        Ok(config)
+    }
+
+    /// Parse the contents of `file`, loading its includes.
+    pub fn parse<P: AsRef<Path>>(xml: &str, file: P) -> Result<Self, ConfigError> {
+        let file = file.as_ref();
+        let mut config = Self::default();
+        config.files.push(file.to_owned());
+        config.load_str(xml, file, &mut vec![file.to_owned()])?;
+
+        Ok(config)
+    }
+
+    /// The files read, in order.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// The `<listen>` elements, in order.
+    pub fn listens(&self) -> &[Listen] {
+        &self.listens
+    }
+
+    /// The mechanisms of the `<auth>` elements.
+    ///
+    /// If empty, dbus-daemon accepts all the mechanisms it supports.
+    pub fn auth(&self) -> &[String] {
+        &self.auth
+    }
+
+    /// Whether `<allow_anonymous/>` is set.
+    pub fn allow_anonymous(&self) -> bool {
+        self.allow_anonymous
+    }
+
+    /// Whether clients may authenticate with `ANONYMOUS`.
+    pub fn accepts_anonymous(&self) -> bool {
+        self.allow_anonymous && (self.auth.is_empty() || self.auth.iter().any(|a| a == "ANONYMOUS"))
+    }
+
+    /// The `tcp:` listeners reachable without authentication.
+    ///
+    /// Without Unix credentials, TCP clients authenticate with `DBUS_COOKIE_SHA1` or `ANONYMOUS`,
+    /// so plain TCP is only protected if anonymous clients are refused. `nonce-tcp:` listeners are
+    /// protected by their nonce.
+    pub fn unprotected_tcp(&self) -> Vec<&Listen> {
+        if !self.accepts_anonymous() {
+            return vec![];
+        }
+
+        self.listens
+            .iter()
+            .filter(|l| {
+                l.parse()
+                    .is_ok_and(|addr| matches!(addr.transport(), Ok(Transport::Tcp(_))))
+            })
+            .collect()
+    }
+
+    fn load_file(
+        &mut self,
+        path: &Path,
+        from: Option<&Location>,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<(), ConfigError> {
+        if stack.len() >= MAX_INCLUDE_DEPTH || stack.iter().any(|p| p == path) {
+            return Err(ConfigError {
+                location: from.cloned(),
+                error: Error::Other(format!("recursive include of `{}`", path.display())),
+            });
+        }
+        let xml = fs::read_to_string(path).map_err(|e| ConfigError {
+            location: from.cloned(),
+            error: Error::Io(format!("{}: {e}", path.display())),
+        })?;
+
+        self.files.push(path.to_owned());
+        stack.push(path.to_owned());
+        let res = self.load_str(&xml, path, stack);
+        stack.pop();
+
+        res
+    }
+
+    fn load_str(
+        &mut self,
+        xml: &str,
+        file: &Path,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<(), ConfigError> {
+        let location = |line| Location {
+            file: file.to_owned(),
+            line,
+        };
+        let root = Parser::new(xml)
+            .document()
+            .map_err(|(line, e)| ConfigError::at(&location(line), Error::Other(e)))?;
+        if root.name != "busconfig" {
+            return Err(ConfigError::at(
+                &location(root.line),
+                Error::Other(format!("expected `<busconfig>`, found `<{}>`", root.name)),
+            ));
+        }
+
+        let dir = file.parent().unwrap_or(Path::new(""));
+        for elem in root.elements() {
+            let location = location(elem.line);
+            match elem.name.as_str() {
+                "listen" => self.listens.push(Listen {
+                    address: elem.text(),
+                    location,
+                }),
+                "auth" => self.auth.push(elem.text()),
+                "allow_anonymous" => self.allow_anonymous = true,
+                "include" => {
+                    // Without SELinux support, like dbus-daemon on most systems.
+                    if elem.attr("if_selinux_enabled") == Some("yes") {
+                        continue;
+                    }
+                    let path = dir.join(elem.text());
+                    if elem.attr("ignore_missing") == Some("yes") && !path.exists() {
+                        continue;
+                    }
+                    self.load_file(&path, Some(&location), stack)?;
+                }
+                "includedir" => {
+                    let path = dir.join(elem.text());
+                    let entries = match fs::read_dir(&path) {
+                        Ok(entries) => entries,
+                        Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                        Err(e) => {
+                            return Err(ConfigError::at(
+                                &location,
+                                Error::Io(format!("{}: {e}", path.display())),
+                            ))
+                        }
+                    };
+                    let mut files: Vec<PathBuf> = entries
+                        .filter_map(|e| e.ok().map(|e| e.path()))
+                        .filter(|p| p.extension().is_some_and(|e| e == "conf"))
+                        .collect();
+                    files.sort();
+                    for file in files {
+                        self.load_file(&file, Some(&location), stack)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+    line: usize,
+}
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|n| match n {
+            Node::Element(e) => Some(e),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    // The trimmed text content.
+    fn text(&self) -> String {
+        let text: String = self
+            .children
+            .iter()
+            .filter_map(|n| match n {
+                Node::Text(t) => Some(t.as_str()),
+                Node::Element(_) => None,
+            })
+            .collect();
+
+        text.trim().to_owned()
+    }
+}
+
+// A small XML parser, enough for bus configuration files: no namespaces, no DTD.
+struct Parser<'a> {
+    rest: &'a str,
+    line: usize,
+}
+
+type ParseResult<T> = Result<T, (usize, String)>;
+
+impl<'a> Parser<'a> {
+    fn new(xml: &'a str) -> Self {
+        Self { rest: xml, line: 1 }
+    }
+
+    fn document(mut self) -> ParseResult<Element> {
+        self.misc()?;
+        if !self.rest.starts_with('<') {
+            return Err(self.error("expected the root element"));
+        }
+        let root = self.element()?;
+        self.misc()?;
+        if !self.rest.is_empty() {
+            return Err(self.error("unexpected content after the root element"));
+        }
+
+        Ok(root)
+    }
+
+    // Skip whitespace, comments, processing instructions and the doctype.
+    fn misc(&mut self) -> ParseResult<()> {
+        loop {
+            self.skip_ws();
+            if self.rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest.starts_with("<!DOCTYPE") {
+                match (self.rest.find('['), self.rest.find('>')) {
+                    (Some(open), Some(close)) if open < close => self.skip_past("]>")?,
+                    _ => self.skip_past(">")?,
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn element(&mut self) -> ParseResult<Element> {
+        let line = self.line;
+        self.advance(1);
+        let name = self.name()?;
+        let mut elem = Element {
+            name,
+            attrs: vec![],
+            children: vec![],
+            line,
+        };
+
+        loop {
+            self.skip_ws();
+            if self.rest.starts_with("/>") {
+                self.advance(2);
+                return Ok(elem);
+            } else if self.rest.starts_with('>') {
+                self.advance(1);
+                break;
+            }
+            let attr = self.name()?;
+            self.skip_ws();
+            self.expect("=")?;
+            self.skip_ws();
+            let quote = match self.rest.chars().next() {
+                Some(q @ ('"' | '\'')) => q,
+                _ => return Err(self.error("expected a quoted attribute value")),
+            };
+            self.advance(1);
+            let end = self
+                .rest
+                .find(quote)
+                .ok_or_else(|| self.error("unterminated attribute value"))?;
+            let value = self.unescape(&self.rest[..end])?;
+            self.advance(end + 1);
+            elem.attrs.push((attr, value));
+        }
+
+        loop {
+            if self.rest.starts_with("</") {
+                self.advance(2);
+                let name = self.name()?;
+                if name != elem.name {
+                    return Err(self.error(&format!(
+                        "mismatched closing tag `</{name}>`, expected `</{}>`",
+                        elem.name
+                    )));
+                }
+                self.skip_ws();
+                self.expect(">")?;
+                return Ok(elem);
+            } else if self.rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if self.rest.starts_with("<![CDATA[") {
+                self.advance("<![CDATA[".len());
+                let end = self
+                    .rest
+                    .find("]]>")
+                    .ok_or_else(|| self.error("unterminated CDATA section"))?;
+                elem.children.push(Node::Text(self.rest[..end].to_owned()));
+                self.advance(end + 3);
+            } else if self.rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest.starts_with('<') {
+                elem.children.push(Node::Element(self.element()?));
+            } else if self.rest.is_empty() {
+                return Err(self.error(&format!("unclosed element `<{}>`", elem.name)));
+            } else {
+                let end = self.rest.find('<').unwrap_or(self.rest.len());
+                let text = self.unescape(&self.rest[..end])?;
+                elem.children.push(Node::Text(text));
+                self.advance(end);
+            }
+        }
+    }
+
+    fn name(&mut self) -> ParseResult<String> {
+        let end = self
+            .rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':')))
+            .unwrap_or(self.rest.len());
+        if end == 0 {
+            return Err(self.error("expected a name"));
+        }
+        let name = self.rest[..end].to_owned();
+        self.advance(end);
+
+        Ok(name)
+    }
+
+    fn unescape(&self, s: &str) -> ParseResult<String> {
+        let mut out = String::with_capacity(s.len());
+        let mut rest = s;
+        while let Some(amp) = rest.find('&') {
+            out.push_str(&rest[..amp]);
+            rest = &rest[amp + 1..];
+            let semi = rest
+                .find(';')
+                .ok_or_else(|| self.error("unterminated entity"))?;
+            let entity = &rest[..semi];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse))
+                    .and_then(|n| n.ok())
+                    .and_then(char::from_u32),
+            };
+            out.push(c.ok_or_else(|| self.error(&format!("unknown entity `&{entity};`")))?);
+            rest = &rest[semi + 1..];
+        }
+        out.push_str(rest);
+
+        Ok(out)
+    }
+
+    fn expect(&mut self, s: &str) -> ParseResult<()> {
+        if !self.rest.starts_with(s) {
+            return Err(self.error(&format!("expected `{s}`")));
+        }
+        self.advance(s.len());
+
+        Ok(())
+    }
+
+    fn skip_past(&mut self, end: &str) -> ParseResult<()> {
+        let pos = self
+            .rest
+            .find(end)
+            .ok_or_else(|| self.error(&format!("expected `{end}`")))?;
+        self.advance(pos + end.len());
+
+        Ok(())
+    }
+
+    fn skip_ws(&mut self) {
+        let len = self.rest.len() - self.rest.trim_start().len();
+        self.advance(len);
+    }
+
+    fn advance(&mut self, len: usize) {
+        self.line += self.rest[..len].matches('\n').count();
+        self.rest = &self.rest[len..];
+    }
+
+    fn error(&self, msg: &str) -> (usize, String) {
+        (self.line, msg.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TmpDir;
+
+    const HEADER: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+"#;
+
+    #[test]
+    fn includes() {
+        let tmp = TmpDir::new("config");
+        let dir = tmp.path();
+        fs::write(
+            dir.join("system.conf"),
+            format!(
+                r#"{HEADER}<busconfig>
+  <!-- <listen>unix:path=/commented</listen> -->
+  <type>system</type>
+  <listen>unix:path=/run/dbus/system_bus_socket</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default"><listen>ignored</listen></policy>
+  <include ignore_missing="yes">missing.conf</include>
+  <include if_selinux_enabled="yes">selinux.conf</include>
+  <includedir>system.d</includedir>
+  <includedir>missing.d</includedir>
+</busconfig>
+"#
+            ),
+        )
+        .unwrap();
+        fs::create_dir(dir.join("system.d")).unwrap();
+        fs::write(
+            dir.join("system.d/b.conf"),
+            "<busconfig><listen>tcp:host=localhost,port=0</listen></busconfig>",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("system.d/a.conf"),
+            "<busconfig>\n<listen>nonce-tcp:host=a&amp;b</listen>\n<allow_anonymous/>\n\
+             <auth>ANONYMOUS</auth></busconfig>",
+        )
+        .unwrap();
+        fs::write(dir.join("system.d/README"), "not xml").unwrap();
+
+        let config = BusConfig::load(dir.join("system.conf")).unwrap();
+        assert_eq!(
+            config.files(),
+            [
+                dir.join("system.conf"),
+                dir.join("system.d/a.conf"),
+                dir.join("system.d/b.conf"),
+            ]
+        );
+        let listens: Vec<_> = config
+            .listens()
+            .iter()
+            .map(|l| (l.address(), l.location().to_string()))
+            .collect();
+        assert_eq!(
+            listens,
+            [
+                (
+                    "unix:path=/run/dbus/system_bus_socket",
+                    format!("{}:7", dir.join("system.conf").display())
+                ),
+                (
+                    "nonce-tcp:host=a&b",
+                    format!("{}:2", dir.join("system.d/a.conf").display())
+                ),
+                (
+                    "tcp:host=localhost,port=0",
+                    format!("{}:1", dir.join("system.d/b.conf").display())
+                ),
+            ]
+        );
+        assert_eq!(config.auth(), ["EXTERNAL", "ANONYMOUS"]);
+        assert!(config.allow_anonymous());
+        assert!(config.accepts_anonymous());
+        let unprotected: Vec<_> = config
+            .unprotected_tcp()
+            .iter()
+            .map(|l| l.address())
+            .collect();
+        assert_eq!(unprotected, ["tcp:host=localhost,port=0"]);
+    }
+
+    #[test]
+    fn errors() {
+        let file = Path::new("/etc/dbus-1/session.conf");
+        let config = BusConfig::parse(
+            "<busconfig>\n\n<listen>tcp:port=99999</listen>\n</busconfig>",
+            file,
+        )
+        .unwrap();
+        assert_eq!(
+            config.listens()[0].parse().unwrap_err().to_string(),
+            "/etc/dbus-1/session.conf:3: Invalid value for key: `port`"
+        );
+        assert!(config.unprotected_tcp().is_empty());
+
+        for (xml, err) in [
+            (
+                "<busconfig>\n<listen>x</auth>",
+                "/etc/dbus-1/session.conf:2: Other error: mismatched closing tag `</auth>`, \
+                 expected `</listen>`",
+            ),
+            (
+                "<busconfig>\n\n<listen>",
+                "/etc/dbus-1/session.conf:3: Other error: unclosed element `<listen>`",
+            ),
+            (
+                "<busconfig>&foo;</busconfig>",
+                "/etc/dbus-1/session.conf:1: Other error: unknown entity `&foo;`",
+            ),
+            (
+                "<config/>",
+                "/etc/dbus-1/session.conf:1: Other error: expected `<busconfig>`, found `<config>`",
+            ),
+            (
+                "<busconfig>\n<include>missing.conf</include></busconfig>",
+                "/etc/dbus-1/session.conf:2: I/O error: /etc/dbus-1/missing.conf: No such file or \
+                 directory (os error 2)",
+            ),
+        ] {
+            assert_eq!(BusConfig::parse(xml, file).unwrap_err().to_string(), err);
+        }
+    }
+
+    #[test]
+    fn recursive_include() {
+        let tmp = TmpDir::new("config-loop");
+        let conf = tmp.path().join("loop.conf");
+        fs::write(&conf, "<busconfig><include>loop.conf</include></busconfig>").unwrap();
+        let err = BusConfig::load(&conf).unwrap_err();
+        assert_eq!(err.location().unwrap().line(), 1);
+        assert_eq!(
+            err.error(),
+            &Error::Other(format!("recursive include of `{}`", conf.display()))
+        );
+    }
+}
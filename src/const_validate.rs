@@ -0,0 +1,797 @@
+//! Compile-time address validation, for the [`crate::dbus_addr!`] macro.
+//!
+//! This mirrors [`crate::DBusAddr`] parsing with `const fn`, so it has to stay in sync with the
+//! transports `for_address()` implementations. It only tells whether an address is valid: the
+//! error itself is the one of `DBusAddr::try_from()`, which is not available at compile time.
+
+/// Create a [`DBusAddr<'static>`](crate::DBusAddr) from a string literal, validated at compile
+/// time.
+///
+/// Example:
+/// ```
+/// use dbus_addr::{dbus_addr, DBusAddr};
+///
+/// const ADDR: DBusAddr<'static> = dbus_addr!("unix:path=/run/foo/bus");
+/// assert_eq!(ADDR.as_str(), "unix:path=/run/foo/bus");
+/// ```
+///
+/// A malformed address fails to compile; `DBusAddr::try_from()` tells why:
+/// ```compile_fail
+/// let _ = dbus_addr::dbus_addr!("unix:pth=/run/foo/bus");
+/// ```
+#[macro_export]
+macro_rules! dbus_addr {
+    ($addr:expr $(,)?) => {{
+        const ADDR: $crate::DBusAddr<'static> = if $crate::__private::validate($addr) {
+            $crate::__private::from_static($addr)
+        } else {
+            ::core::panic!("invalid D-Bus address")
+        };
+        ADDR
+    }};
+}
+
+// `?` is not usable in const fn.
+macro_rules! tri {
+    ($e:expr) => {
+        if !$e {
+            return false;
+        }
+    };
+}
+
+/// Whether an address is valid, like `DBusAddr::try_from()`.
+pub const fn validate(addr: &str) -> bool {
+    validate_bytes(addr.as_bytes())
+}
+
+/// Whether a `;`-separated address list is valid, like `DBusAddrList::validate()`.
+///
+/// The list must not be empty, and a trailing `;` is ignored.
+#[cfg(any(test, feature = "std"))]
+pub(crate) const fn validate_list(list: &str) -> bool {
+    let b = list.as_bytes();
+    if b.is_empty() {
+        return false;
+    }
+
+    let mut rest = b;
+    while !rest.is_empty() {
+        let end = match find(rest, 0, rest.len(), b';') {
+            Some(end) => end,
+            None => rest.len(),
+        };
+        let (entry, next) = rest.split_at(end);
+        tri!(validate_bytes(entry));
+        rest = match next.split_first() {
+            Some((_, next)) => next,
+            None => next,
+        };
+    }
+
+    true
+}
+
+const fn validate_bytes(b: &[u8]) -> bool {
+    let col = match find(b, 0, b.len(), b':') {
+        Some(col) => col,
+        None => return false,
+    };
+    let start = col + 1;
+
+    tri!(if eq(b, 0, col, b"unix") {
+        unix(b, start)
+    } else if eq(b, 0, col, b"launchd") {
+        launchd(b, start)
+    } else if eq(b, 0, col, b"tcp") {
+        tcp(b, start, false)
+    } else if eq(b, 0, col, b"nonce-tcp") {
+        tcp(b, start, true)
+    } else if eq(b, 0, col, b"unixexec") {
+        unixexec(b, start)
+    } else if eq(b, 0, col, b"autolaunch") {
+        autolaunch(b, start)
+    } else if eq(b, 0, col, b"vsock") {
+        vsock(b, start)
+    } else if eq(b, 0, col, b"x-machine-unix") {
+        machine_unix(b, start)
+    } else if eq(b, 0, col, b"x-machine-exec") {
+        machine_exec(b, start)
+    } else {
+        // systemd: and unknown transports have no requirements
+        true
+    });
+
+    let mut i = start;
+    while let Some(kv) = next_kv(b, i) {
+        i = kv.next;
+        if !kv.has_val {
+            continue;
+        }
+        if eq(b, kv.ks, kv.ke, b"guid") {
+            tri!(guid(b, kv.vs, kv.ve));
+        } else {
+            tri!(decoded_bytes(b, kv.vs, kv.ve));
+        }
+    }
+
+    true
+}
+
+#[doc(hidden)]
+pub const fn from_static(addr: &'static str) -> super::DBusAddr<'static> {
+    super::DBusAddr {
+        addr: alloc::borrow::Cow::Borrowed(addr),
+    }
+}
+
+const fn unix(b: &[u8], start: usize) -> bool {
+    let mut found = false;
+    let mut i = start;
+    while let Some(kv) = next_kv(b, i) {
+        i = kv.next;
+        let utf8 = if eq(b, kv.ks, kv.ke, b"path")
+            || eq(b, kv.ks, kv.ke, b"dir")
+            || eq(b, kv.ks, kv.ke, b"tmpdir")
+            || eq(b, kv.ks, kv.ke, b"runtime")
+        {
+            true
+        } else if eq(b, kv.ks, kv.ke, b"abstract") {
+            false
+        } else {
+            continue;
+        };
+        if !kv.has_val {
+            return false;
+        }
+        if utf8 {
+            tri!(decoded_str(b, kv.vs, kv.ve));
+        } else {
+            tri!(decoded_bytes(b, kv.vs, kv.ve));
+        }
+        if eq(b, kv.ks, kv.ke, b"runtime") && !decoded_eq(b, kv.vs, kv.ve, b"yes") {
+            return false;
+        }
+        found = true;
+        break;
+    }
+    if !found {
+        return false;
+    }
+    while let Some(kv) = next_kv(b, i) {
+        i = kv.next;
+        if eq(b, kv.ks, kv.ke, b"path")
+            || eq(b, kv.ks, kv.ke, b"dir")
+            || eq(b, kv.ks, kv.ke, b"tmpdir")
+            || eq(b, kv.ks, kv.ke, b"abstract")
+            || eq(b, kv.ks, kv.ke, b"runtime")
+        {
+            return false;
+        }
+    }
+
+    true
+}
+
+const fn launchd(b: &[u8], start: usize) -> bool {
+    let mut i = start;
+    while let Some(kv) = next_kv(b, i) {
+        i = kv.next;
+        if kv.has_val && eq(b, kv.ks, kv.ke, b"env") {
+            return decoded_str(b, kv.vs, kv.ve);
+        }
+    }
+
+    false
+}
+
+const fn tcp(b: &[u8], start: usize, nonce: bool) -> bool {
+    let mut i = start;
+    while let Some(kv) = next_kv(b, i) {
+        i = kv.next;
+        if !kv.has_val {
+            continue;
+        }
+        if eq(b, kv.ks, kv.ke, b"host")
+            || eq(b, kv.ks, kv.ke, b"bind")
+            || (nonce && eq(b, kv.ks, kv.ke, b"noncefile"))
+        {
+            tri!(decoded_str(b, kv.vs, kv.ve));
+        } else if eq(b, kv.ks, kv.ke, b"port") {
+            tri!(decoded_str(b, kv.vs, kv.ve));
+            tri!(decoded_uint(b, kv.vs, kv.ve, u16::MAX as u64));
+        } else if eq(b, kv.ks, kv.ke, b"family") {
+            tri!(decoded_str(b, kv.vs, kv.ve));
+            if !decoded_eq(b, kv.vs, kv.ve, b"ipv4") && !decoded_eq(b, kv.vs, kv.ve, b"ipv6") {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+const fn unixexec(b: &[u8], start: usize) -> bool {
+    let mut path = false;
+    let mut i = start;
+    while let Some(kv) = next_kv(b, i) {
+        i = kv.next;
+        if !kv.has_val {
+            continue;
+        }
+        if eq(b, kv.ks, kv.ke, b"path") {
+            tri!(decoded_str(b, kv.vs, kv.ve));
+            path = true;
+        } else if kv.ke - kv.ks >= 4 && eq(b, kv.ks, kv.ks + 4, b"argv") {
+            tri!(raw_uint(b, kv.ks + 4, kv.ke));
+            tri!(decoded_str(b, kv.vs, kv.ve));
+        }
+    }
+
+    path
+}
+
+// The `machine` or `pid` key, only one of which is expected.
+const fn machine(b: &[u8], start: usize) -> bool {
+    let mut found = false;
+    let mut i = start;
+    while let Some(kv) = next_kv(b, i) {
+        i = kv.next;
+        if !kv.has_val {
+            continue;
+        }
+        if eq(b, kv.ks, kv.ke, b"machine") {
+            tri!(decoded_str(b, kv.vs, kv.ve));
+        } else if eq(b, kv.ks, kv.ke, b"pid") {
+            tri!(decoded_str(b, kv.vs, kv.ve));
+            tri!(decoded_uint(b, kv.vs, kv.ve, u32::MAX as u64));
+        } else {
+            continue;
+        }
+        if found {
+            return false;
+        }
+        found = true;
+    }
+
+    found
+}
+
+const fn machine_unix(b: &[u8], start: usize) -> bool {
+    tri!(machine(b, start));
+
+    let mut i = start;
+    while let Some(kv) = next_kv(b, i) {
+        i = kv.next;
+        if kv.has_val && eq(b, kv.ks, kv.ke, b"path") {
+            tri!(decoded_str(b, kv.vs, kv.ve));
+        }
+    }
+
+    true
+}
+
+const fn machine_exec(b: &[u8], start: usize) -> bool {
+    tri!(machine(b, start));
+
+    unixexec(b, start)
+}
+
+const fn autolaunch(b: &[u8], start: usize) -> bool {
+    let mut i = start;
+    while let Some(kv) = next_kv(b, i) {
+        i = kv.next;
+        if kv.has_val && eq(b, kv.ks, kv.ke, b"scope") {
+            tri!(decoded_str(b, kv.vs, kv.ve));
+        }
+    }
+
+    true
+}
+
+const fn vsock(b: &[u8], start: usize) -> bool {
+    let mut i = start;
+    while let Some(kv) = next_kv(b, i) {
+        i = kv.next;
+        if !kv.has_val {
+            continue;
+        }
+        if !eq(b, kv.ks, kv.ke, b"port") && !eq(b, kv.ks, kv.ke, b"cid") {
+            continue;
+        }
+        tri!(decoded_str(b, kv.vs, kv.ve));
+        tri!(decoded_uint(b, kv.vs, kv.ve, u32::MAX as u64));
+    }
+
+    true
+}
+
+const fn guid(b: &[u8], start: usize, end: usize) -> bool {
+    tri!(decoded_str(b, start, end));
+
+    let mut len = 0;
+    let mut i = start;
+    while i < end {
+        let (byte, next) = match decode_at(b, i, end) {
+            Some(v) => v,
+            None => return false,
+        };
+        i = next;
+        if !byte.is_ascii_hexdigit() {
+            return false;
+        }
+        len += 1;
+    }
+
+    len == 32
+}
+
+// A `key[=value]` pair of the key-value list, as `KeyValIter` splits it.
+#[derive(Clone, Copy)]
+struct Kv {
+    ks: usize,
+    ke: usize,
+    has_val: bool,
+    vs: usize,
+    ve: usize,
+    next: usize,
+}
+
+const fn next_kv(b: &[u8], i: usize) -> Option<Kv> {
+    if i >= b.len() {
+        return None;
+    }
+
+    let (end, next) = match find(b, i, b.len(), b',') {
+        Some(end) => (end, end + 1),
+        None => (b.len(), b.len()),
+    };
+    let kv = match find(b, i, end, b'=') {
+        Some(eq) => {
+            let ve = match find(b, eq + 1, end, b'=') {
+                Some(ve) => ve,
+                None => end,
+            };
+            Kv {
+                ks: i,
+                ke: eq,
+                has_val: true,
+                vs: eq + 1,
+                ve,
+                next,
+            }
+        }
+        None => Kv {
+            ks: i,
+            ke: end,
+            has_val: false,
+            vs: end,
+            ve: end,
+            next,
+        },
+    };
+
+    Some(kv)
+}
+
+// Decode one byte at `i`, either a plain or a percent-encoded one, and return the next index.
+//
+// Like `decode_percents()`, this works on chars rather than bytes, to fail on the same inputs.
+const fn decode_at(b: &[u8], i: usize, end: usize) -> Option<(u8, usize)> {
+    let c = b[i];
+    if c != b'%' {
+        if is_allowed(c) {
+            return Some((c, i + 1));
+        }
+        return None;
+    }
+
+    let hi = i + 1;
+    if hi >= end {
+        return None;
+    }
+    let lo = hi + char_len(b[hi]);
+    if lo >= end {
+        return None;
+    }
+    let next = lo + char_len(b[lo]);
+    match (hex(b[hi]), hex(b[lo])) {
+        (Some(h), Some(l)) if char_len(b[hi]) == 1 && char_len(b[lo]) == 1 => {
+            Some((h << 4 | l, next))
+        }
+        _ => None,
+    }
+}
+
+const fn decoded_bytes(b: &[u8], start: usize, end: usize) -> bool {
+    let mut i = start;
+    while i < end {
+        let (_, next) = match decode_at(b, i, end) {
+            Some(v) => v,
+            None => return false,
+        };
+        i = next;
+    }
+
+    true
+}
+
+// Check that the value decodes to valid UTF-8.
+const fn decoded_str(b: &[u8], start: usize, end: usize) -> bool {
+    // remaining continuation bytes, and the valid range of the next one
+    let mut need = 0;
+    let mut lo = 0x80;
+    let mut hi = 0xbf;
+    let mut i = start;
+    while i < end {
+        let (byte, next) = match decode_at(b, i, end) {
+            Some(v) => v,
+            None => return false,
+        };
+        i = next;
+        if need > 0 {
+            if byte < lo || byte > hi {
+                return false;
+            }
+            need -= 1;
+            lo = 0x80;
+            hi = 0xbf;
+            continue;
+        }
+        match byte {
+            0x00..=0x7f => {}
+            0xc2..=0xdf => need = 1,
+            0xe0 => (need, lo) = (2, 0xa0),
+            0xe1..=0xec | 0xee..=0xef => need = 2,
+            0xed => (need, hi) = (2, 0x9f),
+            0xf0 => (need, lo) = (3, 0x90),
+            0xf1..=0xf3 => need = 3,
+            0xf4 => (need, hi) = (3, 0x8f),
+            _ => return false,
+        }
+    }
+
+    need == 0
+}
+
+// Whether the decoded value equals `lit`.
+const fn decoded_eq(b: &[u8], start: usize, end: usize, lit: &[u8]) -> bool {
+    let mut n = 0;
+    let mut i = start;
+    while i < end {
+        let (byte, next) = match decode_at(b, i, end) {
+            Some(v) => v,
+            None => return false,
+        };
+        i = next;
+        if n >= lit.len() || byte != lit[n] {
+            return false;
+        }
+        n += 1;
+    }
+
+    n == lit.len()
+}
+
+// Whether the decoded value parses as an unsigned integer up to `max`, like `str::parse()`.
+const fn decoded_uint(b: &[u8], start: usize, end: usize, max: u64) -> bool {
+    let mut val: u64 = 0;
+    let mut digits = 0;
+    let mut i = start;
+    while i < end {
+        let (byte, next) = match decode_at(b, i, end) {
+            Some(v) => v,
+            None => return false,
+        };
+        if i == start && byte == b'+' && next < end {
+            i = next;
+            continue;
+        }
+        i = next;
+        if !byte.is_ascii_digit() {
+            return false;
+        }
+        val = match val.checked_mul(10) {
+            Some(v) => match v.checked_add((byte - b'0') as u64) {
+                Some(v) => v,
+                None => return false,
+            },
+            None => return false,
+        };
+        if val > max {
+            return false;
+        }
+        digits += 1;
+    }
+
+    digits > 0
+}
+
+// Like `decoded_uint()`, for a raw (not encoded) key suffix.
+const fn raw_uint(b: &[u8], start: usize, end: usize) -> bool {
+    let mut val: u64 = 0;
+    let mut i = start;
+    if i < end && b[i] == b'+' && i + 1 < end {
+        i += 1;
+    }
+    if i == end {
+        return false;
+    }
+    while i < end {
+        if !b[i].is_ascii_digit() {
+            return false;
+        }
+        val = match val.checked_mul(10) {
+            Some(v) => match v.checked_add((b[i] - b'0') as u64) {
+                Some(v) => v,
+                None => return false,
+            },
+            None => return false,
+        };
+        if val > usize::MAX as u64 {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
+const fn find(b: &[u8], start: usize, end: usize, c: u8) -> Option<usize> {
+    let mut i = start;
+    while i < end {
+        if b[i] == c {
+            return Some(i);
+        }
+        i += 1;
+    }
+
+    None
+}
+
+const fn eq(b: &[u8], start: usize, end: usize, lit: &[u8]) -> bool {
+    if end - start != lit.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < lit.len() {
+        if b[start + i] != lit[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
+const fn char_len(c: u8) -> usize {
+    match c {
+        0x00..=0x7f => 1,
+        0x80..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    }
+}
+
+const fn hex(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+const fn is_allowed(c: u8) -> bool {
+    matches!(c, b'-' | b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z' | b'_' | b'/' | b'.' | b'\\' | b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::DBusAddr;
+
+    // Addresses checked against the runtime parser.
+    const CORPUS: &[&str] = &[
+        "",
+        "foo",
+        "foo:",
+        "foo:arg=val",
+        "tcp:opt=%1",
+        "tcp:opt=%1z",
+        "tcp:opt=1\rz",
+        "tcp:opt=%\u{e9}",
+        "tcp:opt=%\u{e9}0",
+        "tcp:opt=\u{e9}",
+        "tcp:guid=9406e28972c595c590766c9564ce623",
+        "tcp:guid=9406e28972c595c590766c9564ce623g",
+        "tcp:guid=9406e28972c595c590766c9564ce623f",
+        "tcp:guid=%39406e28972c595c590766c9564ce623",
+        "tcp:guid=%C3%A906e28972c595c590766c9564ce623",
+        "tcp:guid=0%C3%A96e28972c595c590766c9564ce623f",
+        "tcp:guid=+406e28972c595c590766c9564ce623f",
+        "tcp:host=localhost,bind=*,port=0,family=ipv4",
+        "tcp:port=+12",
+        "tcp:port=+",
+        "tcp:port=65536",
+        "tcp:port=-1",
+        "tcp:port=%31%32",
+        "tcp:port",
+        "tcp:family=ipv5",
+        "tcp:family=ipv%36",
+        "tcp:host=%FF",
+        "tcp:host=%C3%A9",
+        "tcp:host=%E0%80%80",
+        "tcp:host=%ED%A0%80",
+        "tcp:host=%F4%90%80%80",
+        "tcp:host=%F0%9F%98%8A",
+        "tcp:host=%F0%9F%98",
+        "tcp:,,host=a=b,,",
+        "nonce-tcp:host=localhost,port=0,family=ipv6,noncefile=foo",
+        "nonce-tcp:noncefile=%FF",
+        "unix:path=/tmp/dbus-foo,guid=9406e28972c595c590766c9564ce623f",
+        "unix:foo=blah",
+        "unix:path",
+        "unix:path=/blah,abstract=foo",
+        "unix:path=/blah,abstract",
+        "unix:abstract=%FF",
+        "unix:tmpdir=%FF",
+        "unix:runtime=no",
+        "unix:runtime=yes",
+        "unix:runtime=y%65s",
+        "unix:dir=/tmp,foo=%1",
+        "launchd:env=FOOBAR",
+        "launchd:weof",
+        "launchd:env",
+        "systemd:",
+        "unixexec:path=/bin/test,argv2=foo",
+        "unixexec:weof",
+        "unixexec:argv1=foo",
+        "unixexec:path=/bin/test,argvx=foo",
+        "unixexec:path=/bin/test,argv=foo",
+        "unixexec:path=/bin/test,argv+1=foo",
+        "unixexec:path=/bin/test,argv1=%FF",
+        "autolaunch:scope=*user",
+        "autolaunch:scope=%FF",
+        "vsock:cid=12,port=32",
+        "vsock:port=abc",
+        "vsock:cid=4294967296",
+        "x-machine-unix:machine=foo",
+        "x-machine-unix:pid=1,path=/run/bus",
+        "x-machine-unix:path=/run/bus",
+        "x-machine-unix:machine=foo,pid=1",
+        "x-machine-unix:machine=foo,machine=bar",
+        "x-machine-unix:pid=-1",
+        "x-machine-unix:pid=4294967296",
+        "x-machine-unix:machine=%FF",
+        "x-machine-unix:pid=1,path=%FF",
+        "x-machine-exec:machine=.host,path=/bin/test,argv1=foo",
+        "x-machine-exec:machine=foo",
+        "x-machine-exec:path=/bin/test",
+        "x-machine-exec:pid=1,pid=2,path=/bin/test",
+        "x-machine-exec:pid=1,path=/bin/test,argvx=foo",
+    ];
+
+    // Check that the compile-time and runtime validity agree, and report the mismatch.
+    fn check(addr: &str) -> Result<(), String> {
+        let runtime = DBusAddr::try_from(addr);
+        if runtime.is_ok() != validate(addr) {
+            return Err(format!("{addr:?}: {runtime:?} at runtime"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn same_as_runtime() {
+        for addr in CORPUS {
+            check(addr).unwrap();
+        }
+    }
+
+    // Fragments likely to break a valid address.
+    const FRAGMENTS: &[&str] = &[
+        "",
+        "%",
+        "%4",
+        "%FF",
+        "%C3%A9",
+        "%00",
+        ",",
+        "=",
+        ";",
+        ":",
+        "guid=",
+        "guid=0",
+        "path=",
+        "path",
+        "dir=/x",
+        "abstract=",
+        "runtime=yes",
+        "runtime=no",
+        "env=",
+        "port=",
+        "port=65536",
+        "port=-1",
+        "family=ipv5",
+        "family=ipv4",
+        "argv1=",
+        "argvx=",
+        "argv+1=a",
+        "pid=1",
+        "pid=-1",
+        "machine=",
+        "cid=4294967296",
+        "\u{e9}",
+        " ",
+    ];
+
+    // A valid address with a fragment inserted at some char position, or a range removed.
+    fn mutated() -> impl Strategy<Value = String> {
+        (
+            any::<crate::OwnedDBusAddr>(),
+            any::<prop::sample::Index>(),
+            any::<prop::sample::Index>(),
+            prop::sample::select(FRAGMENTS),
+            any::<bool>(),
+        )
+            .prop_map(|(addr, i, j, frag, insert)| {
+                let addr = addr.to_string();
+                let bounds: Vec<usize> = addr
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .chain([addr.len()])
+                    .collect();
+                let (i, j) = (i.get(&bounds), j.get(&bounds));
+                let (start, end) = (*i.min(j), *i.max(j));
+                if insert {
+                    format!("{}{frag}{}", &addr[..start], &addr[start..])
+                } else {
+                    format!("{}{}", &addr[..start], &addr[end..])
+                }
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn same_as_runtime_generated(addr in prop_oneof![
+            any::<crate::OwnedDBusAddr>().prop_map(|a| a.to_string()),
+            mutated(),
+            "[a-z-]{0,14}:[a-z0-9/=,%.+-]{0,40}",
+            any::<String>(),
+        ]) {
+            check(&addr).map_err(TestCaseError::fail)?;
+        }
+    }
+
+    #[test]
+    fn list_same_as_runtime() {
+        for list in [
+            "unix:path=/a;tcp:host=h,port=1",
+            "unix:path=/a;",
+            "unix:path=/a;;tcp:",
+            ";unix:path=/a",
+            "",
+            ";",
+            "unix:path=/a;unix:pth=/b",
+            "unix:path=/a;launchd:",
+        ] {
+            let runtime = crate::DBusAddrList::try_from(list).and_then(|l| l.validate());
+            assert_eq!(runtime.is_ok(), validate_list(list), "{list:?}");
+        }
+    }
+
+    #[test]
+    fn macro_expansion() {
+        const ADDR: DBusAddr<'static> = dbus_addr!("unix:path=/run/foo/bus");
+        assert_eq!(ADDR, DBusAddr::try_from("unix:path=/run/foo/bus").unwrap());
+
+        let addr = dbus_addr!("tcp:host=localhost,port=1234,guid=9406e28972c595c590766c9564ce623f");
+        assert_eq!(
+            addr.guid().unwrap().unwrap().to_string(),
+            "9406e28972c595c590766c9564ce623f"
+        );
+    }
+}
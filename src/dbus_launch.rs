@@ -0,0 +1,128 @@
+//! Autolaunch a session bus with `dbus-launch`.
+
+use std::{
+    ffi::OsString,
+    process::{Command, Stdio},
+};
+
+use super::{Error, Guid, LaunchedBus, Result};
+
+/// A `dbus-launch` runner, to autolaunch a session bus.
+///
+/// This follows libdbus behaviour on Unix: `dbus-launch --autolaunch=<machine-id> --binary-syntax
+/// --close-stderr` is executed, and its output is parsed.
+///
+/// Example:
+/// ```no_run
+/// use dbus_addr::{machine_id, DBusLaunch};
+///
+/// let bus = DBusLaunch::new().launch(&machine_id().unwrap()).unwrap();
+/// println!("{}", bus.address());
+/// ```
+#[derive(Debug, Clone)]
+pub struct DBusLaunch {
+    program: OsString,
+}
+
+impl Default for DBusLaunch {
+    fn default() -> Self {
+        Self {
+            program: "dbus-launch".into(),
+        }
+    }
+}
+
+impl DBusLaunch {
+    /// Create a runner for the `dbus-launch` found in the default search path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the launcher program to execute.
+    pub fn program<P: Into<OsString>>(mut self, program: P) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// Run the launcher for the given machine ID.
+    ///
+    /// The launcher either starts a new session bus or returns the address of the one already
+    /// running for the machine.
+    pub fn launch(&self, machine_id: &Guid) -> Result<LaunchedBus> {
+        let output = Command::new(&self.program)
+            .arg(format!("--autolaunch={machine_id}"))
+            .arg("--binary-syntax")
+            .arg("--close-stderr")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()?;
+
+        if !output.status.success() {
+            return Err(Error::Other(format!(
+                "`{}` failed: {}",
+                self.program.to_string_lossy(),
+                output.status
+            )));
+        }
+
+        LaunchedBus::from_binary_syntax(&output.stdout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    use super::*;
+
+    #[test]
+    fn launch() {
+        let dir = crate::tests::TmpDir::new("dbus-launch");
+        let out = dir.path().join("out");
+        let args = dir.path().join("args");
+        fs::write(
+            &out,
+            LaunchedBus::new(
+                "unix:path=/tmp/bus,guid=9406e28972c595c590766c9564ce623f"
+                    .try_into()
+                    .unwrap(),
+                Some(1234),
+                Some(0),
+            )
+            .to_binary_syntax(),
+        )
+        .unwrap();
+        let script = dir.path().join("dbus-launch");
+        fs::write(
+            &script,
+            format!(
+                "#!/bin/sh\necho \"$@\" > {}\ncat {}\n",
+                args.display(),
+                out.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let id = Guid::try_from("9406e28972c595c590766c9564ce623f").unwrap();
+        let bus = DBusLaunch::new().program(&script).launch(&id).unwrap();
+        assert_eq!(
+            bus.address().to_string(),
+            "unix:path=/tmp/bus,guid=9406e28972c595c590766c9564ce623f"
+        );
+        assert_eq!(bus.pid(), Some(1234));
+        assert_eq!(
+            fs::read_to_string(&args).unwrap(),
+            "--autolaunch=9406e28972c595c590766c9564ce623f --binary-syntax --close-stderr\n"
+        );
+
+        fs::write(&script, "#!/bin/sh\nexit 1\n").unwrap();
+        assert!(DBusLaunch::new().program(&script).launch(&id).is_err());
+        assert!(matches!(
+            DBusLaunch::new()
+                .program(dir.path().join("missing"))
+                .launch(&id),
+            Err(Error::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,233 @@
+//! Default bus addresses.
+
+use std::borrow::Cow;
+
+use super::{DBusAddrList, Result};
+
+// Per-OS fallbacks: (target OS, session bus, system bus).
+//
+// A `None` session bus means there is no fixed address, the session bus is looked up in the user
+// runtime directory, or autolaunched.
+const PLATFORM_DEFAULTS: &[(&str, Option<&str>, &str)] = &[
+    (
+        "windows",
+        Some("autolaunch:scope=*user;autolaunch:"),
+        "autolaunch:",
+    ),
+    (
+        "macos",
+        Some("launchd:env=DBUS_LAUNCHD_SESSION_BUS_SOCKET"),
+        "unix:path=/var/run/dbus/system_bus_socket",
+    ),
+    ("linux", None, "unix:path=/run/dbus/system_bus_socket"),
+    ("android", None, "unix:path=/run/dbus/system_bus_socket"),
+];
+
+// Fallbacks for other OSes.
+const OTHER_DEFAULTS: (Option<&str>, &str) = (None, "unix:path=/var/run/dbus/system_bus_socket");
+
+// Distributions may override the system bus address at build time, like libdbus. An invalid
+// address fails the build.
+const SYSTEM_BUS_DEFAULT_ADDRESS: Option<&str> = {
+    let addr = option_env!("DBUS_SYSTEM_BUS_DEFAULT_ADDRESS");
+    if let Some(list) = addr {
+        if !super::const_validate::validate_list(list) {
+            panic!("invalid DBUS_SYSTEM_BUS_DEFAULT_ADDRESS");
+        }
+    }
+    addr
+};
+
+/// Default bus addresses, used when the environment doesn't specify one.
+///
+/// Example:
+/// ```
+/// use dbus_addr::BusDefaults;
+///
+/// let defaults = BusDefaults::builder()
+///     .system("unix:path=/run/dbus/system_bus_socket")
+///     .build()
+///     .unwrap();
+/// assert_eq!(
+///     defaults.system().to_string(),
+///     "unix:path=/run/dbus/system_bus_socket"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BusDefaults {
+    session: Option<DBusAddrList<'static>>,
+    system: DBusAddrList<'static>,
+}
+
+impl Default for BusDefaults {
+    fn default() -> Self {
+        Self::platform()
+    }
+}
+
+impl BusDefaults {
+    /// The defaults of the platform the crate is built for.
+    ///
+    /// The system bus address can be overridden at build time with the
+    /// DBUS_SYSTEM_BUS_DEFAULT_ADDRESS environment variable.
+    pub fn platform() -> Self {
+        let mut defaults = Self::for_os(std::env::consts::OS);
+        if let Some(system) = SYSTEM_BUS_DEFAULT_ADDRESS {
+            // validated at build time
+            defaults.system = DBusAddrList::try_from(system).unwrap();
+        }
+
+        defaults
+    }
+
+    /// The defaults for the given OS, as named by [`std::env::consts::OS`].
+    pub fn for_os(os: &str) -> Self {
+        let (session, system) = PLATFORM_DEFAULTS
+            .iter()
+            .find(|(o, _, _)| *o == os)
+            .map(|(_, session, system)| (*session, *system))
+            .unwrap_or(OTHER_DEFAULTS);
+
+        Self {
+            session: session.map(|s| DBusAddrList::try_from(s).unwrap()),
+            system: DBusAddrList::try_from(system).unwrap(),
+        }
+    }
+
+    /// Create a builder, starting from the platform defaults.
+    pub fn builder() -> BusDefaultsBuilder {
+        BusDefaultsBuilder::new()
+    }
+
+    /// The session bus fallback, if any.
+    pub fn session(&self) -> Option<&DBusAddrList<'static>> {
+        self.session.as_ref()
+    }
+
+    /// The system bus fallback.
+    pub fn system(&self) -> &DBusAddrList<'static> {
+        &self.system
+    }
+}
+
+/// A [`BusDefaults`] builder.
+#[derive(Debug, Clone)]
+pub struct BusDefaultsBuilder {
+    session: Option<Cow<'static, str>>,
+    system: Cow<'static, str>,
+}
+
+impl BusDefaultsBuilder {
+    fn new() -> Self {
+        let defaults = BusDefaults::platform();
+
+        Self {
+            session: defaults.session.map(|s| s.to_string().into()),
+            system: defaults.system.to_string().into(),
+        }
+    }
+
+    /// Set the session bus fallback.
+    pub fn session<S: Into<Cow<'static, str>>>(mut self, session: S) -> Self {
+        self.session = Some(session.into());
+        self
+    }
+
+    /// Unset the session bus fallback.
+    pub fn no_session(mut self) -> Self {
+        self.session = None;
+        self
+    }
+
+    /// Set the system bus fallback.
+    pub fn system<S: Into<Cow<'static, str>>>(mut self, system: S) -> Self {
+        self.system = system.into();
+        self
+    }
+
+    /// Validate the addresses and build the [`BusDefaults`].
+    pub fn build(self) -> Result<BusDefaults> {
+        let session = self.session.map(validate).transpose()?;
+        let system = validate(self.system)?;
+
+        Ok(BusDefaults { session, system })
+    }
+}
+
+fn validate(addr: Cow<'static, str>) -> Result<DBusAddrList<'static>> {
+    let list = match addr {
+        Cow::Borrowed(a) => DBusAddrList::try_from(a)?,
+        Cow::Owned(a) => DBusAddrList::try_from(a)?,
+    };
+    list.validate()?;
+
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn platforms() {
+        let d = BusDefaults::for_os("macos");
+        assert_eq!(
+            d.session().unwrap().to_string(),
+            "launchd:env=DBUS_LAUNCHD_SESSION_BUS_SOCKET"
+        );
+        assert_eq!(
+            d.system().to_string(),
+            "unix:path=/var/run/dbus/system_bus_socket"
+        );
+
+        let d = BusDefaults::for_os("linux");
+        assert_eq!(d.session(), None);
+        assert_eq!(
+            d.system().to_string(),
+            "unix:path=/run/dbus/system_bus_socket"
+        );
+
+        let d = BusDefaults::for_os("windows");
+        assert_eq!(
+            d.session().unwrap().to_string(),
+            "autolaunch:scope=*user;autolaunch:"
+        );
+        assert_eq!(d.system().to_string(), "autolaunch:");
+
+        let d = BusDefaults::for_os("freebsd");
+        assert_eq!(d.session(), None);
+        assert_eq!(
+            d.system().to_string(),
+            "unix:path=/var/run/dbus/system_bus_socket"
+        );
+    }
+
+    #[test]
+    fn builder() {
+        let d = BusDefaults::builder()
+            .session("unix:path=/tmp/session")
+            .system(String::from("tcp:host=localhost,port=1234"))
+            .build()
+            .unwrap();
+        assert_eq!(d.session().unwrap().to_string(), "unix:path=/tmp/session");
+        assert_eq!(d.system().to_string(), "tcp:host=localhost,port=1234");
+
+        let d = BusDefaults::builder().no_session().build().unwrap();
+        assert_eq!(d.session(), None);
+        assert_eq!(d.system(), BusDefaults::platform().system());
+
+        assert_eq!(
+            BusDefaults::builder().system("").build().unwrap_err(),
+            Error::MissingTransport
+        );
+        assert_eq!(
+            BusDefaults::builder()
+                .session("unix:path=/tmp;unix:foo=bar")
+                .build()
+                .unwrap_err()
+                .to_string(),
+            "Other error: invalid `unix:` address, missing required key"
+        );
+    }
+}
@@ -0,0 +1,677 @@
+//! Bus address discovery.
+
+#[cfg(all(unix, not(target_os = "macos")))]
+use std::path::Path;
+use std::{fmt, path::PathBuf};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::PidEnvironment;
+#[cfg(all(unix, not(target_os = "macos")))]
+use super::{encode_percents, SessionBusFile};
+use super::{BusType, DBusAddrList, Environment, Error, ProcessEnvironment, Result};
+
+/// Where a discovered bus address comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AddrSource {
+    /// The given environment variable.
+    Env(&'static str),
+    /// The `$XDG_RUNTIME_DIR/bus` socket.
+    RuntimeDir,
+    /// A running session bus recorded by `dbus-launch` in the given file.
+    SessionBusFile(PathBuf),
+    /// A session bus autolaunched with `dbus-launch`.
+    Autolaunch,
+    /// The platform default address.
+    Default,
+}
+
+impl fmt::Display for AddrSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Env(var) => write!(f, "environment variable `{var}`"),
+            Self::RuntimeDir => write!(f, "runtime directory socket"),
+            Self::SessionBusFile(path) => write!(f, "session bus file `{}`", path.display()),
+            Self::Autolaunch => write!(f, "autolaunch"),
+            Self::Default => write!(f, "platform default"),
+        }
+    }
+}
+
+/// A discovered bus address list, with its source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discovery {
+    address: DBusAddrList<'static>,
+    source: AddrSource,
+}
+
+impl Discovery {
+    fn new(address: DBusAddrList<'static>, source: AddrSource) -> Self {
+        Self { address, source }
+    }
+
+    /// The bus address list.
+    pub fn address(&self) -> &DBusAddrList<'static> {
+        &self.address
+    }
+
+    /// Convert into the bus address list.
+    pub fn into_address(self) -> DBusAddrList<'static> {
+        self.address
+    }
+
+    /// Where the address comes from.
+    pub fn source(&self) -> &AddrSource {
+        &self.source
+    }
+}
+
+/// Get the address for session socket respecting the DBUS_SESSION_BUS_ADDRESS environment
+/// variable.
+///
+/// See [`discover_session_with`] for the lookup rules. On Unix, when no session bus is found, this
+/// spawns `dbus-launch` to start one; use [`session_with`] and [`NoAutolaunch`] to prevent it.
+///
+/// [`NoAutolaunch`]: crate::NoAutolaunch
+pub fn session() -> Result<DBusAddrList<'static>> {
+    session_with(&ProcessEnvironment)
+}
+
+/// Get the address for system bus respecting the DBUS_SYSTEM_BUS_ADDRESS environment
+/// variable.
+///
+/// See [`discover_system_with`] for the lookup rules.
+pub fn system() -> Result<DBusAddrList<'static>> {
+    system_with(&ProcessEnvironment)
+}
+
+/// Get the address of the bus that started this process.
+///
+/// See [`discover_starter_with`] for the lookup rules.
+pub fn starter() -> Result<DBusAddrList<'static>> {
+    starter_with(&ProcessEnvironment)
+}
+
+/// Like [`session`], in the given environment.
+pub fn session_with<E: Environment + ?Sized>(env: &E) -> Result<DBusAddrList<'static>> {
+    discover_session_with(env).map(Discovery::into_address)
+}
+
+/// Like [`system`], in the given environment.
+pub fn system_with<E: Environment + ?Sized>(env: &E) -> Result<DBusAddrList<'static>> {
+    discover_system_with(env).map(Discovery::into_address)
+}
+
+/// Like [`starter`], in the given environment.
+pub fn starter_with<E: Environment + ?Sized>(env: &E) -> Result<DBusAddrList<'static>> {
+    discover_starter_with(env).map(Discovery::into_address)
+}
+
+/// Get the session bus address of a running process.
+///
+/// The environment of the process is read from `/proc/<pid>/environ`, and the lookup rules of
+/// [`discover_session_with`] are applied, without autolaunch. See [`crate::PidEnvironment`].
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn session_for_pid(pid: u32) -> Result<DBusAddrList<'static>> {
+    session_with(&PidEnvironment::new(pid)?)
+}
+
+/// Get the system bus address of a running process.
+///
+/// See [`session_for_pid`].
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn system_for_pid(pid: u32) -> Result<DBusAddrList<'static>> {
+    system_with(&PidEnvironment::new(pid)?)
+}
+
+/// The `/run/user/<uid>/bus` session bus address of a user, as set up by systemd.
+///
+/// The socket is not checked for existence.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn session_for_uid(uid: u32) -> Result<DBusAddrList<'static>> {
+    DBusAddrList::try_from(format!("unix:path=/run/user/{uid}/bus"))
+}
+
+/// Discover the session bus address of the current process.
+pub fn discover_session() -> Result<Discovery> {
+    discover_session_with(&ProcessEnvironment)
+}
+
+/// Discover the system bus address of the current process.
+pub fn discover_system() -> Result<Discovery> {
+    discover_system_with(&ProcessEnvironment)
+}
+
+/// Discover the address of the bus that started the current process.
+pub fn discover_starter() -> Result<Discovery> {
+    discover_starter_with(&ProcessEnvironment)
+}
+
+/// Discover the session bus address in the given environment.
+///
+/// The lookup follows the D-Bus specification and libdbus:
+///
+/// 1. the DBUS_SESSION_BUS_ADDRESS environment variable, if set.
+/// 2. on Unix, the `$XDG_RUNTIME_DIR/bus` socket, if it exists and is owned by the current user.
+/// 3. on Unix, a running session bus recorded in `~/.dbus/session-bus` for `$DISPLAY`.
+/// 4. the session bus fallback of [`Environment::bus_defaults`], if any.
+/// 5. on Unix, a session bus autolaunched with `dbus-launch`.
+///
+/// On Windows and macOS, steps 2, 3 and 5 are skipped.
+///
+/// In secure-execution mode (see [`Environment::secure_execution`]), environment variables are
+/// ignored and autolaunch is refused. Otherwise, a variable that is not valid unicode is an error.
+pub fn discover_session_with<E: Environment + ?Sized>(env: &E) -> Result<Discovery> {
+    if let Some(val) = var(env, "DBUS_SESSION_BUS_ADDRESS")? {
+        return Ok(Discovery::new(
+            DBusAddrList::try_from(val)?,
+            AddrSource::Env("DBUS_SESSION_BUS_ADDRESS"),
+        ));
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        if let Some(runtime_dir) = var_os(env, "XDG_RUNTIME_DIR") {
+            if let Some(addr) = runtime_bus(env, Path::new(&runtime_dir))? {
+                return Ok(Discovery::new(
+                    DBusAddrList::try_from(addr)?,
+                    AddrSource::RuntimeDir,
+                ));
+            }
+        }
+
+        if let (Some(home), Some(display)) = (var_os(env, "HOME"), var(env, "DISPLAY")?) {
+            let file = SessionBusFile::new(home, &env.machine_id()?, &display);
+            if let Some(bus) = file.running_bus()? {
+                return Ok(Discovery::new(
+                    bus.into_address(),
+                    AddrSource::SessionBusFile(file.path().to_owned()),
+                ));
+            }
+        }
+    }
+
+    if let Some(session) = env.bus_defaults().session() {
+        return Ok(Discovery::new(session.clone(), AddrSource::Default));
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        Ok(Discovery::new(autolaunch(env)?, AddrSource::Autolaunch))
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    Err(Error::Other("unable to find the session bus".into()))
+}
+
+/// Discover the system bus address in the given environment.
+///
+/// The DBUS_SYSTEM_BUS_ADDRESS environment variable is used if set, or else the system bus fallback
+/// of [`Environment::bus_defaults`].
+///
+/// In secure-execution mode (see [`Environment::secure_execution`]), the environment variable is
+/// ignored.
+pub fn discover_system_with<E: Environment + ?Sized>(env: &E) -> Result<Discovery> {
+    if let Some(val) = var(env, "DBUS_SYSTEM_BUS_ADDRESS")? {
+        return Ok(Discovery::new(
+            DBusAddrList::try_from(val)?,
+            AddrSource::Env("DBUS_SYSTEM_BUS_ADDRESS"),
+        ));
+    }
+
+    Ok(Discovery::new(
+        env.bus_defaults().system().clone(),
+        AddrSource::Default,
+    ))
+}
+
+/// Discover the address of the bus that started the process, in the given environment.
+///
+/// A service activated by a bus daemon gets the DBUS_STARTER_ADDRESS and DBUS_STARTER_BUS_TYPE
+/// environment variables. The starter address is used if set. Otherwise, the session or system bus
+/// is discovered according to the starter bus type. If neither is set, there is no starter bus.
+pub fn discover_starter_with<E: Environment + ?Sized>(env: &E) -> Result<Discovery> {
+    if let Some(val) = var(env, "DBUS_STARTER_ADDRESS")? {
+        return Ok(Discovery::new(
+            DBusAddrList::try_from(val)?,
+            AddrSource::Env("DBUS_STARTER_ADDRESS"),
+        ));
+    }
+
+    let Some(bus_type) = var(env, "DBUS_STARTER_BUS_TYPE")? else {
+        return Err(Error::Other(
+            "no starter bus: neither DBUS_STARTER_ADDRESS nor DBUS_STARTER_BUS_TYPE is set".into(),
+        ));
+    };
+    match BusType::from_starter_bus_type(&bus_type)? {
+        BusType::System => discover_system_with(env),
+        _ => discover_session_with(env),
+    }
+}
+
+// A session bus autolaunched with `dbus-launch`, also used to connect to `autolaunch:` addresses.
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) fn autolaunch<E: Environment + ?Sized>(env: &E) -> Result<DBusAddrList<'static>> {
+    if env.secure_execution() {
+        return Err(Error::Other(
+            "refusing to autolaunch a session bus in secure-execution mode".into(),
+        ));
+    }
+    let Some(dbus_launch) = env.dbus_launch() else {
+        return Err(Error::Other(
+            "unable to find the session bus, and autolaunch is disabled".into(),
+        ));
+    };
+
+    Ok(dbus_launch.launch(&env.machine_id()?)?.into_address())
+}
+
+// The socket path of a `launchd:` address, from the given environment variable.
+//
+// On macOS, the variable is looked up with `launchctl getenv` if unset, like libdbus does.
+pub(crate) fn launchd_socket<E: Environment + ?Sized>(env: &E, key: &str) -> Result<String> {
+    if let Some(path) = var(env, key)? {
+        return Ok(path);
+    }
+
+    #[cfg(target_os = "macos")]
+    if !env.secure_execution() {
+        let output = std::process::Command::new("launchctl")
+            .args(["getenv", key])
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .output()?;
+        let path = String::from_utf8(output.stdout)
+            .map_err(|_| Error::Encoding(format!("`{key}` is not valid UTF-8")))?;
+        let path = path.trim_end_matches('\n');
+        if output.status.success() && !path.is_empty() {
+            return Ok(path.to_owned());
+        }
+    }
+
+    Err(Error::Other(format!("`{key}` is not set")))
+}
+
+// An environment variable, unless running in secure-execution mode.
+//
+// A value that is not valid unicode is an error, rather than falling back to another source.
+fn var<E: Environment + ?Sized>(env: &E, key: &str) -> Result<Option<String>> {
+    if env.secure_execution() {
+        return Ok(None);
+    }
+
+    match env.var(key) {
+        Some(val) => Ok(Some(val)),
+        None if env.var_os(key).is_some() => {
+            Err(Error::Encoding(format!("`{key}` is not valid UTF-8")))
+        }
+        None => Ok(None),
+    }
+}
+
+// Like `var`, for a path that doesn't need to be valid unicode.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn var_os<E: Environment + ?Sized>(env: &E, key: &str) -> Option<std::ffi::OsString> {
+    if env.secure_execution() {
+        return None;
+    }
+
+    env.var_os(key)
+}
+
+// The `$XDG_RUNTIME_DIR/bus` address, if the socket exists and is owned by the current user.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn runtime_bus<E: Environment + ?Sized>(env: &E, runtime_dir: &Path) -> Result<Option<String>> {
+    let path = runtime_dir.join("bus");
+    if env.socket_owner(&path) != Some(env.euid()) {
+        return Ok(None);
+    }
+    // `path` values must be valid unicode
+    let Some(path) = path.to_str() else {
+        return Err(Error::Encoding(format!(
+            "`{}` is not valid UTF-8",
+            path.display()
+        )));
+    };
+
+    let mut addr = String::from("unix:path=");
+    encode_percents(&mut addr, path.as_bytes()).map_err(|e| Error::Encoding(e.to_string()))?;
+
+    Ok(Some(addr))
+}
+
+#[cfg(all(test, unix, not(target_os = "macos")))]
+mod tests {
+    use std::{fs, os::unix::net::UnixListener};
+
+    use super::*;
+    use crate::{BusDefaults, DBusLaunch, Guid, MapEnvironment, NoAutolaunch, TrustedEnvironment};
+
+    const MACHINE_ID: &str = "9406e28972c595c590766c9564ce623f";
+
+    fn env() -> MapEnvironment {
+        MapEnvironment::new()
+            .with_euid(1000)
+            .with_machine_id(Guid::try_from(MACHINE_ID).unwrap())
+    }
+
+    #[test]
+    fn runtime_dir() {
+        let dir = crate::tests::TmpDir::new("runtime dir");
+        assert_eq!(runtime_bus(&ProcessEnvironment, dir.path()).unwrap(), None);
+
+        fs::write(dir.path().join("bus"), "").unwrap();
+        assert_eq!(runtime_bus(&ProcessEnvironment, dir.path()).unwrap(), None);
+        fs::remove_file(dir.path().join("bus")).unwrap();
+
+        let _listener = UnixListener::bind(dir.path().join("bus")).unwrap();
+        let addr = runtime_bus(&ProcessEnvironment, dir.path())
+            .unwrap()
+            .unwrap();
+        let mut expected = String::from("unix:path=");
+        encode_percents(
+            &mut expected,
+            dir.path().join("bus").to_str().unwrap().as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(addr, expected);
+        assert!(addr.contains("runtime%20dir"));
+        DBusAddrList::try_from(addr).unwrap();
+    }
+
+    #[test]
+    fn session_env() {
+        let env = env()
+            .with_var("DBUS_SESSION_BUS_ADDRESS", "unix:path=/tmp/session")
+            .with_var("XDG_RUNTIME_DIR", "/run/user/1000")
+            .with_socket("/run/user/1000/bus", 1000);
+        let d = discover_session_with(&env).unwrap();
+        assert_eq!(d.address().to_string(), "unix:path=/tmp/session");
+        assert_eq!(d.source(), &AddrSource::Env("DBUS_SESSION_BUS_ADDRESS"));
+    }
+
+    #[test]
+    fn session_runtime_dir() {
+        let env = env()
+            .with_var("XDG_RUNTIME_DIR", "/run/user/1000")
+            .with_socket("/run/user/1000/bus", 1000);
+        let d = discover_session_with(&env).unwrap();
+        assert_eq!(d.address().to_string(), "unix:path=/run/user/1000/bus");
+        assert_eq!(d.source(), &AddrSource::RuntimeDir);
+
+        // not owned by the current user
+        let env = env.with_euid(1001);
+        assert!(discover_session_with(&env).is_err());
+
+        // no socket
+        let env = self::env().with_var("XDG_RUNTIME_DIR", "/run/user/1000");
+        assert!(discover_session_with(&env).is_err());
+    }
+
+    #[test]
+    fn session_bus_file() {
+        let home = crate::tests::TmpDir::new("home");
+        let dir = home.path().join(".dbus").join("session-bus");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{MACHINE_ID}-0"));
+        fs::write(
+            &path,
+            format!(
+                "DBUS_SESSION_BUS_ADDRESS=unix:path=/tmp/x11\nDBUS_SESSION_BUS_PID={}\n",
+                std::process::id()
+            ),
+        )
+        .unwrap();
+
+        let env = env()
+            .with_var("XDG_RUNTIME_DIR", "/run/user/1000")
+            .with_var("HOME", home.path().to_str().unwrap())
+            .with_var("DISPLAY", ":0.0");
+        let d = discover_session_with(&env).unwrap();
+        assert_eq!(d.address().to_string(), "unix:path=/tmp/x11");
+        assert_eq!(d.source(), &AddrSource::SessionBusFile(path));
+
+        let env = env.with_var("DISPLAY", ":1");
+        assert!(discover_session_with(&env).is_err());
+    }
+
+    #[test]
+    fn session_autolaunch() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::tests::TmpDir::new("autolaunch");
+        let script = dir.path().join("dbus-launch");
+        fs::write(
+            &script,
+            "#!/bin/sh\nprintf 'unix:path=/tmp/launched\\000'\n",
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let env = env().with_dbus_launch(DBusLaunch::new().program(&script));
+        let d = discover_session_with(&env).unwrap();
+        assert_eq!(d.address().to_string(), "unix:path=/tmp/launched");
+        assert_eq!(d.source(), &AddrSource::Autolaunch);
+
+        assert!(discover_session_with(&NoAutolaunch(env)).is_err());
+    }
+
+    #[test]
+    fn launchd() {
+        let env = env().with_var("DBUS_LAUNCHD_SESSION_BUS_SOCKET", "/tmp/launchd/bus");
+        assert_eq!(
+            launchd_socket(&env, "DBUS_LAUNCHD_SESSION_BUS_SOCKET").unwrap(),
+            "/tmp/launchd/bus"
+        );
+        assert!(launchd_socket(&env, "DBUS_OTHER_SOCKET").is_err());
+    }
+
+    #[test]
+    fn system_env() {
+        let d = discover_system_with(&env()).unwrap();
+        assert_eq!(
+            d.address().to_string(),
+            "unix:path=/run/dbus/system_bus_socket"
+        );
+        assert_eq!(d.source(), &AddrSource::Default);
+
+        let env = env().with_var("DBUS_SYSTEM_BUS_ADDRESS", "tcp:host=localhost,port=1");
+        let d = discover_system_with(&env).unwrap();
+        assert_eq!(d.address().to_string(), "tcp:host=localhost,port=1");
+        assert_eq!(d.source(), &AddrSource::Env("DBUS_SYSTEM_BUS_ADDRESS"));
+    }
+
+    #[test]
+    fn starter_env() {
+        assert_eq!(
+            discover_starter_with(&env()).unwrap_err(),
+            Error::Other(
+                "no starter bus: neither DBUS_STARTER_ADDRESS nor DBUS_STARTER_BUS_TYPE is set"
+                    .into()
+            )
+        );
+
+        let env = env().with_var("DBUS_STARTER_BUS_TYPE", "system");
+        let d = discover_starter_with(&env).unwrap();
+        assert_eq!(d.source(), &AddrSource::Default);
+
+        let env = env
+            .with_var("DBUS_SYSTEM_BUS_ADDRESS", "unix:path=/tmp/system")
+            .with_var("DBUS_STARTER_ADDRESS", "unix:path=/tmp/starter");
+        let d = discover_starter_with(&env).unwrap();
+        assert_eq!(d.address().to_string(), "unix:path=/tmp/starter");
+        assert_eq!(d.source(), &AddrSource::Env("DBUS_STARTER_ADDRESS"));
+
+        let env = self::env().with_var("DBUS_STARTER_BUS_TYPE", "foo");
+        assert_eq!(
+            starter_with(&env).unwrap_err(),
+            Error::InvalidValue("DBUS_STARTER_BUS_TYPE".into())
+        );
+    }
+
+    #[test]
+    fn secure_execution() {
+        let env = env()
+            .with_var("DBUS_SESSION_BUS_ADDRESS", "unix:path=/tmp/session")
+            .with_var("DBUS_SYSTEM_BUS_ADDRESS", "unix:path=/tmp/system")
+            .with_var("DBUS_STARTER_ADDRESS", "unix:path=/tmp/starter")
+            .with_secure_execution(true)
+            .with_dbus_launch(DBusLaunch::new().program("/nonexistent"));
+
+        let d = discover_system_with(&env).unwrap();
+        assert_eq!(
+            d.address().to_string(),
+            "unix:path=/run/dbus/system_bus_socket"
+        );
+        assert_eq!(d.source(), &AddrSource::Default);
+        assert_eq!(
+            session_with(&env).unwrap_err(),
+            Error::Other("refusing to autolaunch a session bus in secure-execution mode".into())
+        );
+        assert!(starter_with(&env).is_err());
+
+        let env = TrustedEnvironment(env);
+        assert_eq!(
+            system_with(&env).unwrap().to_string(),
+            "unix:path=/tmp/system"
+        );
+        assert_eq!(
+            session_with(&env).unwrap().to_string(),
+            "unix:path=/tmp/session"
+        );
+        assert_eq!(
+            starter_with(&env).unwrap().to_string(),
+            "unix:path=/tmp/starter"
+        );
+    }
+
+    #[test]
+    fn for_uid() {
+        assert_eq!(
+            session_for_uid(1000).unwrap().to_string(),
+            "unix:path=/run/user/1000/bus"
+        );
+    }
+
+    // A shell waiting for its standard input to close, with the given environment.
+    #[cfg(target_os = "linux")]
+    fn child<'a, I>(vars: I) -> std::process::Child
+    where
+        I: IntoIterator<Item = (&'a str, &'a std::ffi::OsStr)>,
+    {
+        use std::{io::Read, process::Stdio};
+
+        let mut child = std::process::Command::new("/bin/sh")
+            .args(["-c", "echo; read _"])
+            .env_clear()
+            .envs(vars)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        // `/proc/<pid>/environ` may still be empty when `spawn()` returns, wait for the shell
+        child.stdout.as_mut().unwrap().read_exact(&mut [0]).unwrap();
+
+        child
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn for_pid() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let mut c = child([
+            (
+                "DBUS_SESSION_BUS_ADDRESS",
+                OsStr::new("unix:path=/tmp/pid-session"),
+            ),
+            (
+                "DBUS_SYSTEM_BUS_ADDRESS",
+                OsStr::new("unix:path=/tmp/pid-system"),
+            ),
+        ]);
+        let session = session_for_pid(c.id());
+        let system = system_for_pid(c.id());
+        drop(c.stdin.take());
+        c.wait().unwrap();
+        assert_eq!(session.unwrap().to_string(), "unix:path=/tmp/pid-session");
+        assert_eq!(system.unwrap().to_string(), "unix:path=/tmp/pid-system");
+
+        // an invalid value is reported, rather than ignored
+        let mut c = child([(
+            "DBUS_SESSION_BUS_ADDRESS",
+            OsStr::from_bytes(b"unix:path=/tmp/\xff"),
+        )]);
+        let session = session_for_pid(c.id());
+        drop(c.stdin.take());
+        c.wait().unwrap();
+        assert_eq!(
+            session.unwrap_err(),
+            Error::Encoding("`DBUS_SESSION_BUS_ADDRESS` is not valid UTF-8".into())
+        );
+
+        assert!(session_for_pid(u32::MAX).is_err());
+    }
+
+    #[test]
+    fn minimal_environment() {
+        // only the required methods: no machine ID and no autolaunch
+        struct Minimal;
+
+        impl Environment for Minimal {
+            fn var(&self, _key: &str) -> Option<String> {
+                None
+            }
+
+            fn euid(&self) -> u32 {
+                1000
+            }
+
+            fn socket_owner(&self, _path: &std::path::Path) -> Option<u32> {
+                None
+            }
+
+            fn secure_execution(&self) -> bool {
+                false
+            }
+        }
+
+        assert!(Minimal.machine_id().is_err());
+        assert!(Minimal.dbus_launch().is_none());
+        assert!(session_with(&Minimal).is_err());
+        assert_eq!(
+            system_with(&Minimal).unwrap().to_string(),
+            "unix:path=/run/dbus/system_bus_socket"
+        );
+    }
+
+    #[test]
+    fn process_not_secure() {
+        // the test binary is not setuid
+        assert!(!ProcessEnvironment.secure_execution());
+    }
+
+    #[test]
+    fn bus_defaults() {
+        let defaults = BusDefaults::builder()
+            .session("unix:path=/tmp/default-session")
+            .system("unix:path=/tmp/default-system")
+            .build()
+            .unwrap();
+        let env = env().with_bus_defaults(defaults);
+
+        let d = discover_session_with(&env).unwrap();
+        assert_eq!(d.address().to_string(), "unix:path=/tmp/default-session");
+        assert_eq!(d.source(), &AddrSource::Default);
+        let d = discover_system_with(&env).unwrap();
+        assert_eq!(d.address().to_string(), "unix:path=/tmp/default-system");
+        assert_eq!(d.source(), &AddrSource::Default);
+
+        // the runtime directory comes first
+        let env = env
+            .with_var("XDG_RUNTIME_DIR", "/run/user/1000")
+            .with_socket("/run/user/1000/bus", 1000);
+        let d = discover_session_with(&env).unwrap();
+        assert_eq!(d.source(), &AddrSource::RuntimeDir);
+    }
+}
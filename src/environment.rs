@@ -0,0 +1,484 @@
+//! Process environment used for bus address discovery.
+
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use super::BusDefaults;
+#[cfg(unix)]
+use super::{DBusLaunch, Guid, Result};
+
+/// The process environment, as seen by bus address discovery.
+///
+/// [`crate::session_with`] and [`crate::system_with`] take an `Environment`, so that discovery
+/// can be run against something else than the current process, such as a [`MapEnvironment`] in
+/// tests.
+pub trait Environment {
+    /// The value of an environment variable, if set and valid unicode.
+    fn var(&self, key: &str) -> Option<String>;
+
+    /// The value of an environment variable, if set, even if not valid unicode.
+    ///
+    /// Discovery uses it to report invalid values, rather than ignoring them. By default, this is
+    /// the value of [`Environment::var`].
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        self.var(key).map(Into::into)
+    }
+
+    /// The effective user ID.
+    fn euid(&self) -> u32;
+
+    /// The user ID owning the socket at `path`, or `None` if there is no such socket.
+    fn socket_owner(&self, path: &Path) -> Option<u32>;
+
+    /// Whether the process runs in secure-execution mode, such as a setuid or setgid binary.
+    ///
+    /// In this mode, the environment is controlled by a less privileged user, so discovery ignores
+    /// it, like libdbus does. Use [`TrustedEnvironment`] to opt out.
+    fn secure_execution(&self) -> bool;
+
+    /// The default bus addresses, used when the environment doesn't specify one.
+    fn bus_defaults(&self) -> BusDefaults {
+        BusDefaults::platform()
+    }
+
+    /// The local machine ID, unknown by default.
+    #[cfg(unix)]
+    fn machine_id(&self) -> Result<Guid> {
+        Err(super::Error::Other("unable to find the machine ID".into()))
+    }
+
+    /// The `dbus-launch` runner used to autolaunch a session bus, or `None` to disable autolaunch.
+    ///
+    /// Autolaunch is disabled by default.
+    #[cfg(unix)]
+    fn dbus_launch(&self) -> Option<DBusLaunch> {
+        None
+    }
+}
+
+/// The environment of the current process.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessEnvironment;
+
+impl Environment for ProcessEnvironment {
+    fn var(&self, key: &str) -> Option<String> {
+        env::var(key).ok()
+    }
+
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        env::var_os(key)
+    }
+
+    #[cfg(unix)]
+    fn euid(&self) -> u32 {
+        #[link(name = "c")]
+        extern "C" {
+            fn geteuid() -> u32;
+        }
+
+        unsafe { geteuid() }
+    }
+
+    #[cfg(not(unix))]
+    fn euid(&self) -> u32 {
+        0
+    }
+
+    #[cfg(unix)]
+    fn socket_owner(&self, path: &Path) -> Option<u32> {
+        use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+        let meta = path.metadata().ok()?;
+        meta.file_type().is_socket().then(|| meta.uid())
+    }
+
+    #[cfg(not(unix))]
+    fn socket_owner(&self, _path: &Path) -> Option<u32> {
+        None
+    }
+
+    // AT_SECURE is set by the kernel for setuid/setgid binaries, file capabilities or LSM
+    // transitions, this is what `secure_getenv()` checks.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn secure_execution(&self) -> bool {
+        const AT_SECURE: std::ffi::c_ulong = 23;
+
+        #[link(name = "c")]
+        extern "C" {
+            fn getauxval(type_: std::ffi::c_ulong) -> std::ffi::c_ulong;
+        }
+
+        unsafe { getauxval(AT_SECURE) != 0 }
+    }
+
+    #[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+    fn secure_execution(&self) -> bool {
+        #[link(name = "c")]
+        extern "C" {
+            fn getuid() -> u32;
+            fn geteuid() -> u32;
+            fn getgid() -> u32;
+            fn getegid() -> u32;
+        }
+
+        unsafe { getuid() != geteuid() || getgid() != getegid() }
+    }
+
+    #[cfg(not(unix))]
+    fn secure_execution(&self) -> bool {
+        false
+    }
+
+    #[cfg(unix)]
+    fn machine_id(&self) -> Result<Guid> {
+        super::machine_id()
+    }
+
+    #[cfg(unix)]
+    fn dbus_launch(&self) -> Option<DBusLaunch> {
+        Some(DBusLaunch::new())
+    }
+}
+
+/// An in-memory environment, for tests.
+///
+/// Nothing is read from the current process: variables and sockets must be added explicitly, and
+/// autolaunch is disabled unless a launcher is given.
+///
+/// Example:
+/// ```
+/// use dbus_addr::{session_with, MapEnvironment};
+///
+/// let env = MapEnvironment::new().with_var("DBUS_SESSION_BUS_ADDRESS", "unix:path=/tmp/bus");
+/// assert_eq!(
+///     session_with(&env).unwrap().to_string(),
+///     "unix:path=/tmp/bus"
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MapEnvironment {
+    vars: HashMap<String, String>,
+    euid: u32,
+    sockets: HashMap<PathBuf, u32>,
+    secure_execution: bool,
+    bus_defaults: BusDefaults,
+    #[cfg(unix)]
+    machine_id: Option<Guid>,
+    #[cfg(unix)]
+    dbus_launch: Option<DBusLaunch>,
+}
+
+impl MapEnvironment {
+    /// Create an empty environment, for user ID 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set an environment variable.
+    pub fn with_var<K: Into<String>, V: Into<String>>(mut self, key: K, val: V) -> Self {
+        self.vars.insert(key.into(), val.into());
+        self
+    }
+
+    /// Set the effective user ID.
+    pub fn with_euid(mut self, euid: u32) -> Self {
+        self.euid = euid;
+        self
+    }
+
+    /// Add a socket owned by the given user ID.
+    pub fn with_socket<P: Into<PathBuf>>(mut self, path: P, owner: u32) -> Self {
+        self.sockets.insert(path.into(), owner);
+        self
+    }
+
+    /// Set the secure-execution mode.
+    pub fn with_secure_execution(mut self, secure_execution: bool) -> Self {
+        self.secure_execution = secure_execution;
+        self
+    }
+
+    /// Set the default bus addresses.
+    pub fn with_bus_defaults(mut self, bus_defaults: BusDefaults) -> Self {
+        self.bus_defaults = bus_defaults;
+        self
+    }
+
+    /// Set the local machine ID.
+    #[cfg(unix)]
+    pub fn with_machine_id(mut self, machine_id: Guid) -> Self {
+        self.machine_id = Some(machine_id);
+        self
+    }
+
+    /// Set the `dbus-launch` runner, enabling autolaunch.
+    #[cfg(unix)]
+    pub fn with_dbus_launch(mut self, dbus_launch: DBusLaunch) -> Self {
+        self.dbus_launch = Some(dbus_launch);
+        self
+    }
+}
+
+impl Environment for MapEnvironment {
+    fn var(&self, key: &str) -> Option<String> {
+        self.vars.get(key).cloned()
+    }
+
+    fn euid(&self) -> u32 {
+        self.euid
+    }
+
+    fn socket_owner(&self, path: &Path) -> Option<u32> {
+        self.sockets.get(path).copied()
+    }
+
+    fn secure_execution(&self) -> bool {
+        self.secure_execution
+    }
+
+    fn bus_defaults(&self) -> BusDefaults {
+        self.bus_defaults.clone()
+    }
+
+    #[cfg(unix)]
+    fn machine_id(&self) -> Result<Guid> {
+        self.machine_id
+            .clone()
+            .ok_or_else(|| super::Error::Other("unable to find the machine ID".into()))
+    }
+
+    #[cfg(unix)]
+    fn dbus_launch(&self) -> Option<DBusLaunch> {
+        self.dbus_launch.clone()
+    }
+}
+
+/// The environment of another process, read from `/proc/<pid>/environ`.
+///
+/// Variables that are not valid unicode are kept, and reported by discovery if used. The effective
+/// user ID is the one of the process, while the secure-execution mode, the machine ID and the
+/// socket lookups are the ones of the current process. Autolaunch is disabled.
+///
+/// Example:
+/// ```no_run
+/// use dbus_addr::{session_with, PidEnvironment};
+///
+/// let env = PidEnvironment::new(1234).unwrap();
+/// println!("{}", session_with(&env).unwrap());
+/// ```
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Debug, Clone)]
+pub struct PidEnvironment {
+    pid: u32,
+    vars: HashMap<OsString, OsString>,
+    euid: u32,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl PidEnvironment {
+    /// Read the environment of the process.
+    pub fn new(pid: u32) -> Result<Self> {
+        let proc = Path::new("/proc").join(pid.to_string());
+        let vars = parse_environ(&std::fs::read(proc.join("environ"))?);
+        let status = std::fs::read_to_string(proc.join("status"))?;
+        // `Uid:` lists the real, effective, saved and filesystem user IDs
+        let euid = status
+            .lines()
+            .find_map(|l| l.strip_prefix("Uid:"))
+            .and_then(|uids| uids.split_whitespace().nth(1))
+            .and_then(|euid| euid.parse().ok())
+            .ok_or_else(|| super::Error::Other(format!("invalid `{}/status`", proc.display())))?;
+
+        Ok(Self { pid, vars, euid })
+    }
+
+    /// The process ID.
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Environment for PidEnvironment {
+    fn var(&self, key: &str) -> Option<String> {
+        self.var_os(key)?.into_string().ok()
+    }
+
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        self.vars.get(std::ffi::OsStr::new(key)).cloned()
+    }
+
+    fn euid(&self) -> u32 {
+        self.euid
+    }
+
+    fn socket_owner(&self, path: &Path) -> Option<u32> {
+        ProcessEnvironment.socket_owner(path)
+    }
+
+    // the variables may come from a less privileged user, as in the current process
+    fn secure_execution(&self) -> bool {
+        ProcessEnvironment.secure_execution()
+    }
+
+    fn machine_id(&self) -> Result<Guid> {
+        super::machine_id()
+    }
+}
+
+// The NUL-separated `KEY=value` entries of `/proc/<pid>/environ`, which may not be valid unicode.
+// The first value of a key wins, like `getenv()`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_environ(environ: &[u8]) -> HashMap<OsString, OsString> {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let mut vars = HashMap::new();
+    for entry in environ.split(|b| *b == 0) {
+        let Some(eq) = entry.iter().position(|b| *b == b'=') else {
+            continue;
+        };
+        let (key, val) = (&entry[..eq], &entry[eq + 1..]);
+        vars.entry(OsStr::from_bytes(key).to_owned())
+            .or_insert_with(|| OsStr::from_bytes(val).to_owned());
+    }
+
+    vars
+}
+
+/// An environment trusted even in secure-execution mode.
+///
+/// By default, discovery ignores the environment variables of a setuid or setgid process, since an
+/// attacker could point a privileged helper at a hostile bus. Wrapping an environment in
+/// `TrustedEnvironment` is an explicit opt-in to use them anyway.
+///
+/// Example:
+/// ```no_run
+/// use dbus_addr::{system_with, ProcessEnvironment, TrustedEnvironment};
+///
+/// let addr = system_with(&TrustedEnvironment(ProcessEnvironment)).unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TrustedEnvironment<E>(pub E);
+
+impl<E: Environment> Environment for TrustedEnvironment<E> {
+    fn var(&self, key: &str) -> Option<String> {
+        self.0.var(key)
+    }
+
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        self.0.var_os(key)
+    }
+
+    fn euid(&self) -> u32 {
+        self.0.euid()
+    }
+
+    fn socket_owner(&self, path: &Path) -> Option<u32> {
+        self.0.socket_owner(path)
+    }
+
+    fn secure_execution(&self) -> bool {
+        false
+    }
+
+    fn bus_defaults(&self) -> BusDefaults {
+        self.0.bus_defaults()
+    }
+
+    #[cfg(unix)]
+    fn machine_id(&self) -> Result<Guid> {
+        self.0.machine_id()
+    }
+
+    #[cfg(unix)]
+    fn dbus_launch(&self) -> Option<DBusLaunch> {
+        self.0.dbus_launch()
+    }
+}
+
+/// An environment with autolaunch disabled.
+///
+/// Discovery with [`ProcessEnvironment`] may run `dbus-launch` to start a session bus as a last
+/// resort. Wrapping it in `NoAutolaunch` only looks up existing buses, as an inspection tool
+/// should.
+///
+/// Example:
+/// ```no_run
+/// use dbus_addr::{discover_session_with, NoAutolaunch, ProcessEnvironment};
+///
+/// let discovery = discover_session_with(&NoAutolaunch(ProcessEnvironment));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NoAutolaunch<E>(pub E);
+
+impl<E: Environment> Environment for NoAutolaunch<E> {
+    fn var(&self, key: &str) -> Option<String> {
+        self.0.var(key)
+    }
+
+    fn var_os(&self, key: &str) -> Option<OsString> {
+        self.0.var_os(key)
+    }
+
+    fn euid(&self) -> u32 {
+        self.0.euid()
+    }
+
+    fn socket_owner(&self, path: &Path) -> Option<u32> {
+        self.0.socket_owner(path)
+    }
+
+    fn secure_execution(&self) -> bool {
+        self.0.secure_execution()
+    }
+
+    fn bus_defaults(&self) -> BusDefaults {
+        self.0.bus_defaults()
+    }
+
+    #[cfg(unix)]
+    fn machine_id(&self) -> Result<Guid> {
+        self.0.machine_id()
+    }
+
+    #[cfg(unix)]
+    fn dbus_launch(&self) -> Option<DBusLaunch> {
+        None
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environ() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let vars = parse_environ(b"A=1\0B=x=y\0C=\xff\0A=2\0EMPTY=\0junk\0\0");
+        assert_eq!(
+            vars,
+            HashMap::from([
+                ("A".into(), "1".into()),
+                ("B".into(), "x=y".into()),
+                ("C".into(), OsString::from_vec(b"\xff".to_vec())),
+                ("EMPTY".into(), "".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn pid_environment() {
+        let env = PidEnvironment::new(std::process::id()).unwrap();
+        assert_eq!(env.pid(), std::process::id());
+        assert_eq!(env.euid(), ProcessEnvironment.euid());
+        assert_eq!(env.var("PATH"), env::var("PATH").ok());
+        assert!(env.dbus_launch().is_none());
+
+        assert!(PidEnvironment::new(u32::MAX).is_err());
+    }
+}
@@ -1,6 +1,6 @@
 //! D-Bus address GUID.
 
-use std::fmt;
+use core::fmt;
 
 use super::{Error, Result};
 
@@ -21,7 +21,7 @@ impl Guid {
 }
 
 impl fmt::Display for Guid {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}{:02x}",
                self.0[0], self.0[1], self.0[2], self.0[3], self.0[4], self.0[5], self.0[6], self.0[7],
                self.0[8], self.0[9], self.0[10], self.0[11], self.0[12], self.0[13], self.0[14], self.0[15])
@@ -32,14 +32,14 @@ impl TryFrom<&str> for Guid {
     type Error = Error;
 
     fn try_from(s: &str) -> Result<Self> {
-        if s.len() != 32 {
+        if s.len() != 32 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
             return Err(Error::InvalidValue("guid".into()));
         }
 
         let mut bytes = [0u8; 16];
         for (i, chunk) in s.as_bytes().chunks(2).enumerate() {
-            bytes[i] = u8::from_str_radix(std::str::from_utf8(chunk).unwrap(), 16)
-                .map_err(|_| Error::InvalidValue("guid".into()))?;
+            // the chunks are ASCII hex digits, checked above
+            bytes[i] = u8::from_str_radix(core::str::from_utf8(chunk).unwrap(), 16).unwrap();
         }
 
         Ok(Guid(bytes))
@@ -0,0 +1,555 @@
+//! `DBUS_COOKIE_SHA1` keyrings.
+//!
+//! A server proves that a client can read the user's `~/.dbus-keyrings/<context>` file: it sends
+//! the ID of a secret cookie from the keyring and a challenge, the client answers with its own
+//! challenge and the SHA-1 hash of both challenges and the cookie.
+//!
+//! Each line of a keyring file holds a cookie ID, its creation time in seconds since the Unix epoch
+//! and the hex-encoded cookie. The keyring directory must only be accessible by its owner, and
+//! writers serialize with a `<context>.lock` file.
+//!
+//! <https://dbus.freedesktop.org/doc/dbus-specification.html#auth-mechanisms-sha>
+//!
+//! Example:
+//! ```no_run
+//! use dbus_addr::keyring::{self, Keyring, DEFAULT_CONTEXT};
+//!
+//! let keyring = Keyring::new().unwrap();
+//! let cookie = keyring.current_cookie(DEFAULT_CONTEXT).unwrap();
+//! let client_challenge = keyring::challenge().unwrap();
+//! let response = cookie.response("server-challenge", &client_challenge);
+//! ```
+
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::{auth::hex, sha1::sha1, Error, Result};
+
+/// The context used when none is configured.
+pub const DEFAULT_CONTEXT: &str = "org_freedesktop_general";
+
+// Age after which a server creates a new cookie, and after which cookies are removed, like libdbus.
+const NEW_COOKIE_AGE: u64 = 5 * 60;
+const EXPIRE_COOKIE_AGE: u64 = 7 * 60;
+// Cookies created further in the future are removed.
+const MAX_TIME_TRAVEL: u64 = 5 * 60;
+const MAX_COOKIES: usize = 256;
+
+const COOKIE_LEN: usize = 24;
+const CHALLENGE_LEN: usize = 24;
+const LOCK_RETRY: Duration = Duration::from_millis(250);
+
+/// A keyring cookie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    id: u32,
+    created: u64,
+    value: String,
+}
+
+impl Cookie {
+    /// The cookie ID.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The creation time.
+    pub fn created(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.created)
+    }
+
+    /// The hex-encoded secret.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// The hex-encoded SHA-1 of `<server_challenge>:<client_challenge>:<cookie>`.
+    ///
+    /// The client sends it with its challenge, the server computes it to check the client answer.
+    pub fn response(&self, server_challenge: &str, client_challenge: &str) -> String {
+        let data = format!("{server_challenge}:{client_challenge}:{}", self.value);
+
+        hex(&sha1(data.as_bytes()))
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split(' ');
+        let (Some(id), Some(created), Some(value), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return None;
+        };
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        Some(Self {
+            id: id.parse().ok()?,
+            created: created.parse().ok()?,
+            value: value.to_owned(),
+        })
+    }
+
+    fn is_valid_at(&self, now: u64) -> bool {
+        now.saturating_sub(self.created) <= EXPIRE_COOKIE_AGE
+            && self.created.saturating_sub(now) <= MAX_TIME_TRAVEL
+    }
+}
+
+/// A random hex-encoded challenge.
+pub fn challenge() -> Result<String> {
+    Ok(hex(&random_bytes(CHALLENGE_LEN)?))
+}
+
+/// A directory of keyrings, such as `~/.dbus-keyrings`.
+#[derive(Debug, Clone)]
+pub struct Keyring {
+    dir: PathBuf,
+    lock_timeout: Duration,
+}
+
+impl Keyring {
+    /// The keyring of the current user, in `$HOME/.dbus-keyrings`.
+    pub fn new() -> Result<Self> {
+        let home = env::var_os("HOME")
+            .ok_or_else(|| Error::Other("HOME is not set, can't locate the keyring".into()))?;
+
+        Ok(Self::with_dir(Path::new(&home).join(".dbus-keyrings")))
+    }
+
+    /// A keyring in the given directory.
+    pub fn with_dir<P: Into<PathBuf>>(dir: P) -> Self {
+        Self {
+            dir: dir.into(),
+            lock_timeout: Duration::from_secs(8),
+        }
+    }
+
+    /// Set how long to wait for the lock file before considering it stale.
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
+    }
+
+    /// The keyring directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The valid cookies of `context`.
+    ///
+    /// A missing keyring has no cookies.
+    pub fn cookies(&self, context: &str) -> Result<Vec<Cookie>> {
+        check_context(context)?;
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+        self.check_dir()?;
+
+        Ok(self.load(context, now())?.0)
+    }
+
+    /// The cookie of `context` with the given ID, as needed by a client.
+    pub fn cookie(&self, context: &str, id: u32) -> Result<Cookie> {
+        self.cookies(context)?
+            .into_iter()
+            .find(|c| c.id == id)
+            .ok_or_else(|| Error::Other(format!("no cookie {id} in keyring `{context}`")))
+    }
+
+    /// A recent cookie of `context`, as needed by a server.
+    ///
+    /// The keyring is created if needed, expired cookies are removed, and a new cookie is added if
+    /// the others are getting old.
+    pub fn current_cookie(&self, context: &str) -> Result<Cookie> {
+        self.current_cookie_at(context, now())
+    }
+
+    fn current_cookie_at(&self, context: &str, now: u64) -> Result<Cookie> {
+        check_context(context)?;
+        self.create_dir()?;
+        let _lock = Lock::acquire(&self.dir.join(format!("{context}.lock")), self.lock_timeout)?;
+
+        let (mut cookies, mut changed) = self.load(context, now)?;
+        let recent = cookies
+            .iter()
+            .filter(|c| now.saturating_sub(c.created) < NEW_COOKIE_AGE)
+            .max_by_key(|c| c.created)
+            .cloned();
+        let cookie = match recent {
+            Some(cookie) => cookie,
+            None => {
+                let cookie = Cookie {
+                    id: self.new_id(&cookies)?,
+                    created: now,
+                    value: hex(&random_bytes(COOKIE_LEN)?),
+                };
+                if cookies.len() >= MAX_COOKIES {
+                    cookies.remove(0);
+                }
+                cookies.push(cookie.clone());
+                changed = true;
+                cookie
+            }
+        };
+        if changed {
+            self.save(context, &cookies)?;
+        }
+
+        Ok(cookie)
+    }
+
+    // The valid cookies, and whether invalid ones were dropped.
+    fn load(&self, context: &str, now: u64) -> Result<(Vec<Cookie>, bool)> {
+        let contents = match fs::read_to_string(self.dir.join(context)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((vec![], false)),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut dropped = false;
+        let mut cookies: Vec<Cookie> = vec![];
+        for line in contents.lines() {
+            match Cookie::parse(line) {
+                Some(c) if c.is_valid_at(now) && !cookies.iter().any(|o| o.id == c.id) => {
+                    cookies.push(c)
+                }
+                _ => dropped = true,
+            }
+        }
+        if cookies.len() > MAX_COOKIES {
+            cookies.drain(..cookies.len() - MAX_COOKIES);
+            dropped = true;
+        }
+
+        Ok((cookies, dropped))
+    }
+
+    // Replace the keyring file atomically, readable by its owner only.
+    fn save(&self, context: &str, cookies: &[Cookie]) -> Result<()> {
+        let tmp = self
+            .dir
+            .join(format!("{context}.{}.tmp", hex(&random_bytes(4)?)));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let res = options.open(&tmp).and_then(|mut file| {
+            for c in cookies {
+                writeln!(file, "{} {} {}", c.id, c.created, c.value)?;
+            }
+            file.sync_all()?;
+            fs::rename(&tmp, self.dir.join(context))
+        });
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+
+        Ok(res?)
+    }
+
+    fn new_id(&self, cookies: &[Cookie]) -> Result<u32> {
+        loop {
+            let bytes = random_bytes(4)?;
+            let id = u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) & 0x7fff_ffff;
+            if !cookies.iter().any(|c| c.id == id) {
+                return Ok(id);
+            }
+        }
+    }
+
+    fn create_dir(&self) -> Result<()> {
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        match builder.create(&self.dir) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => self.check_dir(),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Like libdbus, refuse a keyring that other users could tamper with.
+    #[cfg(unix)]
+    fn check_dir(&self) -> Result<()> {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        use super::{Environment, ProcessEnvironment};
+
+        let meta = fs::symlink_metadata(&self.dir)?;
+        let dir = self.dir.display();
+        if !meta.is_dir() {
+            return Err(Error::Other(format!("keyring `{dir}` is not a directory")));
+        }
+        if meta.uid() != ProcessEnvironment.euid() {
+            return Err(Error::Other(format!(
+                "keyring `{dir}` is owned by another user"
+            )));
+        }
+        if meta.permissions().mode() & 0o077 != 0 {
+            return Err(Error::Other(format!(
+                "keyring `{dir}` is accessible by other users"
+            )));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn check_dir(&self) -> Result<()> {
+        if !fs::metadata(&self.dir)?.is_dir() {
+            return Err(Error::Other(format!(
+                "keyring `{}` is not a directory",
+                self.dir.display()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+// The context names a file in the keyring directory.
+fn check_context(context: &str) -> Result<()> {
+    if context.is_empty()
+        || !context.is_ascii()
+        || context.contains(['/', '\\', ' ', '\n', '\r', '\t', '.'])
+    {
+        return Err(Error::Other(format!("invalid keyring context `{context}`")));
+    }
+
+    Ok(())
+}
+
+// A lock file, removed on drop.
+struct Lock(PathBuf);
+
+impl Lock {
+    fn acquire(path: &Path, timeout: Duration) -> Result<Self> {
+        let start = Instant::now();
+        let create = || OpenOptions::new().write(true).create_new(true).open(path);
+        loop {
+            match create() {
+                Ok(_) => return Ok(Self(path.to_owned())),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+            if start.elapsed() >= timeout {
+                break;
+            }
+            thread::sleep(LOCK_RETRY.min(timeout));
+        }
+
+        // The lock is stale, like libdbus break it.
+        fs::remove_file(path)?;
+        create()?;
+
+        Ok(Self(path.to_owned()))
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(unix)]
+pub(crate) fn random_bytes(len: usize) -> Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut bytes = vec![0; len];
+    fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+#[cfg(windows)]
+pub(crate) fn random_bytes(len: usize) -> Result<Vec<u8>> {
+    const BCRYPT_USE_SYSTEM_PREFERRED_RNG: u32 = 2;
+
+    #[link(name = "bcrypt")]
+    extern "system" {
+        fn BCryptGenRandom(
+            algorithm: *mut std::ffi::c_void,
+            buffer: *mut u8,
+            len: u32,
+            flags: u32,
+        ) -> i32;
+    }
+
+    let mut bytes = vec![0; len];
+    let status = unsafe {
+        BCryptGenRandom(
+            std::ptr::null_mut(),
+            bytes.as_mut_ptr(),
+            len.try_into()
+                .map_err(|_| Error::Other("too many random bytes requested".into()))?,
+            BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+        )
+    };
+    if status != 0 {
+        return Err(Error::Other(format!(
+            "BCryptGenRandom failed with status {status:#x}"
+        )));
+    }
+
+    Ok(bytes)
+}
+
+// Without a known OS random number generator, cookies and challenges can't be generated safely.
+#[cfg(not(any(unix, windows)))]
+pub(crate) fn random_bytes(_len: usize) -> Result<Vec<u8>> {
+    Err(Error::Other(
+        "no secure random number generator on this platform".into(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::TmpDir;
+
+    const T: u64 = 1_700_000_000;
+
+    #[test]
+    fn response() {
+        let cookie = Cookie::parse("1 1700000000 0123abcd").unwrap();
+        assert_eq!(
+            cookie.response("server", "client"),
+            "09613f6645693d3a2e4e7ec41d2799988f7aad59"
+        );
+        assert_eq!(Cookie::parse("1 1700000000 xyz"), None);
+        assert_eq!(Cookie::parse("1 1700000000"), None);
+        assert_eq!(challenge().unwrap().len(), CHALLENGE_LEN * 2);
+    }
+
+    #[test]
+    fn context() {
+        assert!(check_context(DEFAULT_CONTEXT).is_ok());
+        for ctx in ["", "a/b", "..", "a b", "é"] {
+            assert!(check_context(ctx).is_err(), "{ctx}");
+        }
+    }
+
+    #[test]
+    fn rotate() {
+        let tmp = TmpDir::new("keyring");
+        let keyring = Keyring::with_dir(tmp.path().join("keyrings"));
+        assert_eq!(keyring.cookies(DEFAULT_CONTEXT).unwrap(), vec![]);
+
+        let first = keyring.current_cookie_at(DEFAULT_CONTEXT, T).unwrap();
+        assert_eq!(first.value().len(), COOKIE_LEN * 2);
+        assert!(!tmp
+            .path()
+            .join("keyrings/org_freedesktop_general.lock")
+            .exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = |p: &str| {
+                let p = tmp.path().join(p);
+                fs::metadata(p).unwrap().permissions().mode() & 0o777
+            };
+            assert_eq!(mode("keyrings"), 0o700);
+            assert_eq!(mode("keyrings/org_freedesktop_general"), 0o600);
+        }
+
+        // reused while recent, then a new one is added
+        let same = keyring
+            .current_cookie_at(DEFAULT_CONTEXT, T + NEW_COOKIE_AGE - 1)
+            .unwrap();
+        assert_eq!(same, first);
+        let second = keyring
+            .current_cookie_at(DEFAULT_CONTEXT, T + NEW_COOKIE_AGE)
+            .unwrap();
+        assert_ne!(second.id(), first.id());
+        assert_eq!(
+            keyring.load(DEFAULT_CONTEXT, T + NEW_COOKIE_AGE).unwrap(),
+            (vec![first.clone(), second.clone()], false)
+        );
+
+        // the first one expires
+        let now = T + EXPIRE_COOKIE_AGE + 1;
+        assert_eq!(
+            keyring.current_cookie_at(DEFAULT_CONTEXT, now).unwrap(),
+            second
+        );
+        assert_eq!(
+            keyring.load(DEFAULT_CONTEXT, now).unwrap(),
+            (vec![second.clone()], false)
+        );
+    }
+
+    #[test]
+    fn invalid_lines() {
+        let tmp = TmpDir::new("keyring-lines");
+        let keyring = Keyring::with_dir(tmp.path().join("keyrings"));
+        keyring.create_dir().unwrap();
+        let now = now();
+        fs::write(
+            keyring.dir().join("ctx"),
+            format!(
+                "1 {now} abcd\ngarbage\n2 {} abcd\n3 {} abcd\n1 {now} ef01\n",
+                now + MAX_TIME_TRAVEL + 10,
+                now - EXPIRE_COOKIE_AGE - 10
+            ),
+        )
+        .unwrap();
+        let cookies = keyring.cookies("ctx").unwrap();
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(keyring.cookie("ctx", 1).unwrap().value(), "abcd");
+        assert!(keyring.cookie("ctx", 2).is_err());
+
+        // the server rewrites the keyring without them
+        let cookie = keyring.current_cookie("ctx").unwrap();
+        assert_eq!(cookie.id(), 1);
+        assert_eq!(
+            fs::read_to_string(keyring.dir().join("ctx")).unwrap(),
+            format!("1 {now} abcd\n")
+        );
+    }
+
+    #[test]
+    fn stale_lock() {
+        let tmp = TmpDir::new("keyring-lock");
+        let keyring =
+            Keyring::with_dir(tmp.path().join("keyrings")).lock_timeout(Duration::from_millis(10));
+        keyring.create_dir().unwrap();
+        fs::File::create(keyring.dir().join("ctx.lock")).unwrap();
+        keyring.current_cookie("ctx").unwrap();
+        assert!(!keyring.dir().join("ctx.lock").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = TmpDir::new("keyring-perm");
+        let keyring = Keyring::with_dir(tmp.path().join("keyrings"));
+        keyring.create_dir().unwrap();
+        fs::set_permissions(keyring.dir(), fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(
+            keyring.current_cookie("ctx").unwrap_err(),
+            Error::Other(format!(
+                "keyring `{}` is accessible by other users",
+                keyring.dir().display()
+            ))
+        );
+        assert!(keyring.cookies("ctx").is_err());
+    }
+}
@@ -1,5 +1,10 @@
 #![doc = include_str!("../README.md")]
-use std::{env, fmt};
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use core::fmt;
 
 pub mod transport;
 
@@ -18,11 +23,106 @@ pub use percent::*;
 mod guid;
 pub use guid::Guid;
 
+#[cfg(all(feature = "std", unix))]
+mod machine_id;
+#[cfg(all(feature = "std", unix))]
+pub use machine_id::{machine_id, machine_id_from_file};
+
+#[cfg(all(feature = "std", unix))]
+mod dbus_launch;
+#[cfg(all(feature = "std", unix))]
+pub use dbus_launch::DBusLaunch;
+
+#[cfg(all(feature = "std", unix))]
+mod session_bus_file;
+#[cfg(all(feature = "std", unix))]
+pub use session_bus_file::SessionBusFile;
+
+#[cfg(feature = "std")]
+mod defaults;
+#[cfg(feature = "std")]
+pub use defaults::{BusDefaults, BusDefaultsBuilder};
+
+#[cfg(feature = "std")]
+mod environment;
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+pub use environment::PidEnvironment;
+#[cfg(feature = "std")]
+pub use environment::{
+    Environment, MapEnvironment, NoAutolaunch, ProcessEnvironment, TrustedEnvironment,
+};
+
+#[cfg(feature = "std")]
+mod discover;
+#[cfg(all(feature = "std", unix, not(target_os = "macos")))]
+pub use discover::session_for_uid;
+#[cfg(feature = "std")]
+pub use discover::{
+    discover_session, discover_session_with, discover_starter, discover_starter_with,
+    discover_system, discover_system_with, session, session_with, starter, starter_with, system,
+    system_with, AddrSource, Discovery,
+};
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+pub use discover::{session_for_pid, system_for_pid};
+
+mod const_validate;
+#[doc(hidden)]
+pub mod __private {
+    pub use super::const_validate::{from_static, validate};
+}
+
+mod bus_type;
+pub use bus_type::BusType;
+
+#[cfg(feature = "std")]
+mod probe;
+#[cfg(feature = "std")]
+pub use probe::{DBusStream, Endpoint, ProbeOptions, ProbeReport};
+
+#[cfg(feature = "std")]
+mod root;
+#[cfg(feature = "std")]
+pub use root::RootDir;
+
+#[cfg(feature = "std")]
+pub mod auth;
+
+#[cfg(feature = "std")]
+pub mod keyring;
+
+#[cfg(feature = "std")]
+pub mod config;
+
+#[cfg(feature = "std")]
+mod command;
+#[cfg(feature = "std")]
+pub use command::DBusCommandExt;
+
+#[cfg(feature = "std")]
+mod shell;
+#[cfg(feature = "std")]
+pub use shell::LaunchedBus;
+
+#[cfg(feature = "std")]
+mod sha1;
+
+#[cfg(feature = "test-util")]
+pub mod testing;
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+
+#[cfg(any(test, feature = "proptest"))]
+pub mod proptest;
+
 #[cfg(test)]
 mod tests;
 
 /// Error returned when an address is invalid.
+///
+/// New variants may be added in minor releases: matches should have a wildcard arm.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
     MissingTransport,
     Encoding(String),
@@ -31,11 +131,12 @@ pub enum Error {
     MissingValue(String),
     InvalidValue(String),
     UnknownTcpFamily(String),
+    Io(String),
     Other(String),
 }
 
 impl fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Error::MissingTransport => write!(f, "Missing transport in address"),
             Error::Encoding(e) => write!(f, "Encoding error: {e}"),
@@ -44,67 +145,23 @@ impl fmt::Display for Error {
             Error::MissingValue(e) => write!(f, "Missing value for key: `{e}`"),
             Error::InvalidValue(e) => write!(f, "Invalid value for key: `{e}`"),
             Error::UnknownTcpFamily(e) => write!(f, "Unknown TCP address family: `{e}`"),
+            Error::Io(e) => write!(f, "I/O error: {e}"),
             Error::Other(e) => write!(f, "Other error: {e}"),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
-pub type Result<T> = std::result::Result<T, Error>;
-
-/// Get the address for session socket respecting the DBUS_SESSION_BUS_ADDRESS environment
-/// variable. If we don't recognize the value (or it's not set) we fall back to
-/// $XDG_RUNTIME_DIR/bus
-pub fn session() -> Result<DBusAddrList<'static>> {
-    match env::var("DBUS_SESSION_BUS_ADDRESS") {
-        Ok(val) => DBusAddrList::try_from(val),
-        _ => {
-            #[cfg(windows)]
-            {
-                DBusAddrList::try_from("autolaunch:scope=*user;autolaunch:")
-            }
-
-            #[cfg(all(unix, not(target_os = "macos")))]
-            {
-                #[link(name = "c")]
-                extern "C" {
-                    fn geteuid() -> u32;
-                }
-
-                let runtime_dir = env::var("XDG_RUNTIME_DIR")
-                    .unwrap_or_else(|_| format!("/run/user/{}", unsafe { geteuid() }));
-                let path = format!("unix:path={runtime_dir}/bus");
-
-                DBusAddrList::try_from(path)
-            }
-
-            #[cfg(target_os = "macos")]
-            {
-                DBusAddrList::try_from("launchd:env=DBUS_LAUNCHD_SESSION_BUS_SOCKET")
-            }
-        }
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e.to_string())
     }
 }
 
-/// Get the address for system bus respecting the DBUS_SYSTEM_BUS_ADDRESS environment
-/// variable. If we don't recognize the value (or it's not set) we fall back to
-/// /var/run/dbus/system_bus_socket
-pub fn system() -> Result<DBusAddrList<'static>> {
-    match env::var("DBUS_SYSTEM_BUS_ADDRESS") {
-        Ok(val) => DBusAddrList::try_from(val),
-        _ => {
-            #[cfg(all(unix, not(target_os = "macos")))]
-            return DBusAddrList::try_from("unix:path=/var/run/dbus/system_bus_socket");
-
-            #[cfg(windows)]
-            return DBusAddrList::try_from("autolaunch:");
-
-            #[cfg(target_os = "macos")]
-            return DBusAddrList::try_from("launchd:env=DBUS_LAUNCHD_SESSION_BUS_SOCKET");
-        }
-    }
-}
+pub type Result<T> = core::result::Result<T, Error>;
 
 struct KeyValIter<'a> {
     data: &'a str,
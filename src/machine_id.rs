@@ -0,0 +1,54 @@
+//! Local machine ID.
+
+use std::{fs, path::Path};
+
+use super::{Error, Guid, Result};
+
+// Locations searched for the machine ID, in libdbus order.
+const MACHINE_ID_FILES: &[&str] = &["/var/lib/dbus/machine-id", "/etc/machine-id"];
+
+/// Read the local machine ID.
+///
+/// The machine ID is looked up in `/var/lib/dbus/machine-id`, then in `/etc/machine-id`.
+///
+/// <https://dbus.freedesktop.org/doc/dbus-specification.html#uuids>
+pub fn machine_id() -> Result<Guid> {
+    for file in MACHINE_ID_FILES {
+        match machine_id_from_file(file) {
+            Err(Error::Io(_)) => continue,
+            res => return res,
+        }
+    }
+
+    Err(Error::Other("unable to find the machine ID".into()))
+}
+
+/// Read the machine ID from the given file.
+pub fn machine_id_from_file(path: impl AsRef<Path>) -> Result<Guid> {
+    let id = fs::read_to_string(path)?;
+
+    Guid::try_from(id.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_file() {
+        let dir = crate::tests::TmpDir::new("machine-id");
+        let path = dir.path().join("machine-id");
+        fs::write(&path, "9406e28972c595c590766c9564ce623f\n").unwrap();
+        let id = machine_id_from_file(&path).unwrap();
+        assert_eq!(id.to_string(), "9406e28972c595c590766c9564ce623f");
+
+        fs::write(&path, "not-a-machine-id\n").unwrap();
+        assert_eq!(
+            machine_id_from_file(&path).unwrap_err(),
+            Error::InvalidValue("guid".into())
+        );
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(machine_id_from_file(&path), Err(Error::Io(_))));
+    }
+}
@@ -1,4 +1,5 @@
-use std::fmt;
+use alloc::string::String;
+use core::fmt;
 
 use super::{transport, transport::TransportImpl, DBusAddr, Error, Guid, KeyValFmt, Result};
 
@@ -17,6 +18,10 @@ pub struct OwnedDBusAddr {
 }
 
 impl OwnedDBusAddr {
+    pub(crate) fn from_parts(transport: transport::Transport<'static>, guid: Option<Guid>) -> Self {
+        Self { transport, guid }
+    }
+
     /// The connection GUID if any.
     pub fn guid(&self) -> Option<&Guid> {
         self.guid.as_ref()
@@ -31,7 +36,7 @@ impl OwnedDBusAddr {
         let addr = DBusAddr { addr: addr.into() };
         let transport = addr.transport()?.into_owned();
         let guid = addr.guid()?;
-        Ok(Self { transport, guid })
+        Ok(Self::from_parts(transport, guid))
     }
 }
 
@@ -61,6 +66,13 @@ impl TryFrom<String> for OwnedDBusAddr {
     }
 }
 
+impl From<transport::Transport<'_>> for OwnedDBusAddr {
+    /// An address without GUID.
+    fn from(transport: transport::Transport<'_>) -> Self {
+        Self::from_parts(transport.into_owned(), None)
+    }
+}
+
 /// A trait for objects which can be converted or resolved to one or more [`OwnedDBusAddr`] values.
 pub trait ToOwnedDBusAddrs<'a> {
     type Iter: Iterator<Item = Result<OwnedDBusAddr>>;
@@ -70,9 +82,17 @@ pub trait ToOwnedDBusAddrs<'a> {
 }
 
 impl<'a> ToOwnedDBusAddrs<'a> for str {
-    type Iter = std::iter::Once<Result<OwnedDBusAddr>>;
+    type Iter = core::iter::Once<Result<OwnedDBusAddr>>;
+
+    fn to_owned_dbus_addrs(&'a self) -> Self::Iter {
+        core::iter::once(self.try_into())
+    }
+}
+
+impl<'a> ToOwnedDBusAddrs<'a> for OwnedDBusAddr {
+    type Iter = core::iter::Once<Result<OwnedDBusAddr>>;
 
     fn to_owned_dbus_addrs(&'a self) -> Self::Iter {
-        std::iter::once(self.try_into())
+        core::iter::once(Ok(self.clone()))
     }
 }
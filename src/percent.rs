@@ -1,13 +1,15 @@
-use std::{
+use alloc::{
     borrow::Cow,
-    ffi::{OsStr, OsString},
-    fmt,
+    format,
+    string::{String, ToString},
+    vec::Vec,
 };
+use core::fmt;
 
 use super::{Error, Result};
 
 /// Percent-encode the value.
-pub fn encode_percents(f: &mut dyn fmt::Write, value: &[u8]) -> std::fmt::Result {
+pub fn encode_percents(f: &mut dyn fmt::Write, value: &[u8]) -> core::fmt::Result {
     for &byte in value {
         if matches!(byte, b'-' | b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z' | b'_' | b'/' | b'.' | b'\\' | b'*')
         {
@@ -55,11 +57,11 @@ pub fn decode_percents(value: &str) -> Result<Cow<'_, [u8]>> {
 
 // A trait for types that can be percent-encoded and written to a [`fmt::Formatter`].
 pub(crate) trait Encodable {
-    fn encode(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result;
+    fn encode(&self, f: &mut fmt::Formatter<'_>) -> core::fmt::Result;
 }
 
 impl<T: ToString> Encodable for T {
-    fn encode(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+    fn encode(&self, f: &mut fmt::Formatter<'_>) -> core::fmt::Result {
         encode_percents(f, self.to_string().as_bytes())
     }
 }
@@ -67,22 +69,20 @@ impl<T: ToString> Encodable for T {
 pub(crate) struct EncData<T: ?Sized>(pub T);
 
 impl<T: AsRef<[u8]>> Encodable for EncData<T> {
-    fn encode(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+    fn encode(&self, f: &mut fmt::Formatter<'_>) -> core::fmt::Result {
         encode_percents(f, self.0.as_ref())
     }
 }
 
-pub(crate) struct EncOsStr<T: ?Sized>(pub T);
-
-impl Encodable for EncOsStr<&Cow<'_, OsStr>> {
-    fn encode(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
-        encode_percents(f, self.0.to_string_lossy().as_bytes())
-    }
-}
-
-impl Encodable for EncOsStr<&OsStr> {
-    fn encode(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
-        encode_percents(f, self.0.to_string_lossy().as_bytes())
+// A path as an address value, which must be valid UTF-8.
+#[cfg(feature = "std")]
+pub(crate) fn path_value(path: std::path::PathBuf) -> Result<Cow<'static, str>> {
+    match path.into_os_string().into_string() {
+        Ok(path) => Ok(Cow::Owned(path)),
+        Err(path) => Err(Error::Encoding(format!(
+            "`{}` is not valid UTF-8",
+            path.to_string_lossy()
+        ))),
     }
 }
 
@@ -116,7 +116,7 @@ pub(super) fn decode_percents_str(value: &str) -> Result<Cow<'_, str>> {
 fn cow_bytes_to_str(cow: Cow<'_, [u8]>) -> Result<Cow<'_, str>> {
     match cow {
         Cow::Borrowed(bytes) => Ok(Cow::Borrowed(
-            std::str::from_utf8(bytes).map_err(|e| Error::Encoding(format!("{e}")))?,
+            core::str::from_utf8(bytes).map_err(|e| Error::Encoding(format!("{e}")))?,
         )),
         Cow::Owned(bytes) => Ok(Cow::Owned(
             String::from_utf8(bytes).map_err(|e| Error::Encoding(format!("{e}")))?,
@@ -124,21 +124,6 @@ fn cow_bytes_to_str(cow: Cow<'_, [u8]>) -> Result<Cow<'_, str>> {
     }
 }
 
-pub(super) fn decode_percents_os_str(value: &str) -> Result<Cow<'_, OsStr>> {
-    cow_bytes_to_os_str(decode_percents(value)?)
-}
-
-fn cow_bytes_to_os_str(cow: Cow<'_, [u8]>) -> Result<Cow<'_, OsStr>> {
-    match cow {
-        Cow::Borrowed(bytes) => Ok(Cow::Borrowed(OsStr::new(
-            std::str::from_utf8(bytes).map_err(|e| Error::Encoding(format!("{e}")))?,
-        ))),
-        Cow::Owned(bytes) => Ok(Cow::Owned(OsString::from(
-            String::from_utf8(bytes).map_err(|e| Error::Encoding(format!("{e}")))?,
-        ))),
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,770 @@
+//! Connectivity probes.
+
+use std::{
+    borrow::Cow,
+    fmt, fs,
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::{process::Child, sync::Arc};
+
+use super::{
+    transport::{MachineRef, NonceTcp, TcpFamily, Transport, UnixAddrKind},
+    DBusAddr, Error, ProcessEnvironment, Result, RootDir,
+};
+
+/// Options of a connectivity probe.
+///
+/// Example:
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use dbus_addr::{DBusAddrList, ProbeOptions};
+///
+/// let list = DBusAddrList::try_from("unix:path=/run/dbus/system_bus_socket").unwrap();
+/// let options = ProbeOptions::new().timeout(Duration::from_millis(500));
+/// for report in list.probe_with(&options) {
+///     println!("{}: {:?}", report.address(), report.error());
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProbeOptions {
+    timeout: Duration,
+    auth: bool,
+    root: Option<RootDir>,
+    autolaunch: bool,
+}
+
+impl Default for ProbeOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(2),
+            auth: true,
+            root: None,
+            autolaunch: false,
+        }
+    }
+}
+
+impl ProbeOptions {
+    /// Create options with a 2 seconds timeout, checking that a D-Bus server answers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the timeout of the connection, and of each read and write once connected.
+    ///
+    /// A TCP connection, trying each address of the host in turn, must succeed within the timeout.
+    /// Host name resolution, `unix:` connections and the start of `unixexec:` commands can't be
+    /// interrupted: they are not bounded.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Whether to send the initial NUL byte and an `AUTH` line, and wait for the server answer.
+    ///
+    /// Otherwise, the probe only checks that the connection succeeds.
+    pub fn auth(mut self, auth: bool) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Resolve `unix:` socket paths under the given root directory, such as a container one.
+    pub fn root(mut self, root: RootDir) -> Self {
+        self.root = Some(root);
+        self
+    }
+
+    /// Whether to autolaunch a session bus with `dbus-launch` for `autolaunch:` addresses.
+    ///
+    /// Disabled by default, since the launched bus outlives the probe.
+    pub fn autolaunch(mut self, autolaunch: bool) -> Self {
+        self.autolaunch = autolaunch;
+        self
+    }
+}
+
+/// The endpoint a probe connected to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Endpoint {
+    /// A Unix domain socket path.
+    Unix(PathBuf),
+    /// A Unix domain socket in the abstract namespace.
+    Abstract(Vec<u8>),
+    /// A TCP socket address.
+    Tcp(SocketAddr),
+    /// A command line, run with its standard input and output connected to the stream.
+    Exec(String),
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unix(path) => write!(f, "{}", path.display()),
+            Self::Abstract(name) => write!(f, "@{}", String::from_utf8_lossy(name)),
+            Self::Tcp(addr) => write!(f, "{addr}"),
+            Self::Exec(command) => write!(f, "{command}"),
+        }
+    }
+}
+
+/// The result of probing an address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeReport {
+    address: String,
+    endpoint: Option<Endpoint>,
+    latency: Option<Duration>,
+    mechanisms: Option<Vec<String>>,
+    error: Option<Error>,
+}
+
+impl ProbeReport {
+    /// The probed address.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// The resolved endpoint, if any.
+    pub fn endpoint(&self) -> Option<&Endpoint> {
+        self.endpoint.as_ref()
+    }
+
+    /// The time to connect, and get the server answer if requested.
+    ///
+    /// `None` if the probe failed.
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    /// The authentication mechanisms announced by the server, if requested.
+    pub fn mechanisms(&self) -> Option<&[String]> {
+        self.mechanisms.as_deref()
+    }
+
+    /// The reason the probe failed, if it did.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Whether the probe succeeded.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    pub(crate) fn failed(address: &str, error: Error) -> Self {
+        Self {
+            address: address.to_owned(),
+            endpoint: None,
+            latency: None,
+            mechanisms: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// A stream connected to a bus, see [`DBusAddr::connect`].
+///
+/// Nothing is sent on connection, except the nonce of `nonce-tcp:` addresses: the D-Bus
+/// authentication is left to the caller. The command of a `unixexec:` address is killed once the
+/// stream and all its clones are dropped.
+#[derive(Debug)]
+pub struct DBusStream {
+    stream: Stream,
+    endpoint: Option<Endpoint>,
+}
+
+impl DBusStream {
+    /// The endpoint connected to.
+    pub fn endpoint(&self) -> Option<&Endpoint> {
+        self.endpoint.as_ref()
+    }
+
+    /// Create an independently owned handle to the same socket.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let stream = match &self.stream {
+            Stream::Tcp(s) => Stream::Tcp(s.try_clone()?),
+            #[cfg(unix)]
+            Stream::Unix(s) => Stream::Unix(s.try_clone()?),
+            #[cfg(unix)]
+            Stream::Exec(s, child) => Stream::Exec(s.try_clone()?, child.clone()),
+        };
+
+        Ok(Self {
+            stream,
+            endpoint: self.endpoint.clone(),
+        })
+    }
+
+    /// Shut down the read, write, or both halves of the connection.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match &self.stream {
+            Stream::Tcp(s) => s.shutdown(how),
+            #[cfg(unix)]
+            Stream::Unix(s) | Stream::Exec(s, _) => s.shutdown(how),
+        }
+    }
+}
+
+impl Read for DBusStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.as_rw().read(buf)
+    }
+}
+
+impl Write for DBusStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.as_rw().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.as_rw().flush()
+    }
+}
+
+// A connected stream.
+#[derive(Debug)]
+enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixStream),
+    // connected to the standard input and output of the child
+    #[cfg(unix)]
+    Exec(std::os::unix::net::UnixStream, Arc<ExecChild>),
+}
+
+impl Stream {
+    fn set_timeout(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            Self::Tcp(s) => {
+                s.set_read_timeout(Some(timeout))?;
+                s.set_write_timeout(Some(timeout))
+            }
+            #[cfg(unix)]
+            Self::Unix(s) | Self::Exec(s, _) => {
+                s.set_read_timeout(Some(timeout))?;
+                s.set_write_timeout(Some(timeout))
+            }
+        }
+    }
+
+    fn as_rw(&mut self) -> &mut dyn ReadWrite {
+        match self {
+            Self::Tcp(s) => s,
+            #[cfg(unix)]
+            Self::Unix(s) | Self::Exec(s, _) => s,
+        }
+    }
+}
+
+// A `unixexec:` command, killed on drop.
+#[cfg(unix)]
+#[derive(Debug)]
+struct ExecChild(Child);
+
+#[cfg(unix)]
+impl Drop for ExecChild {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+trait ReadWrite: Read + Write {}
+
+impl<T: Read + Write> ReadWrite for T {}
+
+pub(crate) fn probe(addr: &DBusAddr<'_>, options: &ProbeOptions) -> ProbeReport {
+    let start = Instant::now();
+    let mut endpoint = None;
+    let mut mechanisms = None;
+    let res = addr.transport().and_then(|transport| {
+        let transport = match &options.root {
+            Some(root) => root.resolve_transport(&transport)?,
+            None => transport,
+        };
+        let mut stream = connect(&transport, options, &mut endpoint)?;
+        if options.auth {
+            stream.set_timeout(options.timeout)?;
+            mechanisms = Some(auth(stream.as_rw())?);
+        }
+
+        Ok(())
+    });
+
+    ProbeReport {
+        address: addr.as_str().to_owned(),
+        endpoint,
+        latency: res.is_ok().then(|| start.elapsed()),
+        mechanisms,
+        error: res.err(),
+    }
+}
+
+pub(crate) fn connect_addr(addr: &DBusAddr<'_>, options: &ProbeOptions) -> Result<DBusStream> {
+    let transport = addr.transport()?;
+    let transport = match &options.root {
+        Some(root) => root.resolve_transport(&transport)?,
+        None => transport,
+    };
+    let mut endpoint = None;
+    let stream = connect(&transport, options, &mut endpoint)?;
+
+    Ok(DBusStream { stream, endpoint })
+}
+
+fn connect(
+    transport: &Transport<'_>,
+    options: &ProbeOptions,
+    endpoint: &mut Option<Endpoint>,
+) -> Result<Stream> {
+    let timeout = options.timeout;
+    match transport {
+        Transport::Unix(unix) => connect_unix(unix.kind(), endpoint),
+        Transport::Tcp(tcp) => {
+            connect_tcp(tcp.host(), tcp.port(), tcp.family(), timeout, endpoint).map(Stream::Tcp)
+        }
+        Transport::NonceTcp(nonce_tcp) => connect_nonce_tcp(nonce_tcp, timeout, endpoint),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Transport::MachineUnix(machine_unix) => {
+            let path = crate::percent::path_value(machine_unix.resolve()?)?;
+            connect_unix(&UnixAddrKind::Path(path), endpoint)
+        }
+        Transport::Unixexec(exec) => connect_exec(&[], exec.path(), exec.argv(), endpoint),
+        Transport::MachineExec(exec) => {
+            // the command is run in the machine namespaces by nsenter(1)
+            let pid = match exec.machine() {
+                MachineRef::Name(name) if name == ".host" => None,
+                MachineRef::Pid(pid) => Some(pid.to_string()),
+                MachineRef::Name(name) => {
+                    return Err(Error::Other(format!(
+                        "unable to resolve machine `{name}` without systemd-machined"
+                    )))
+                }
+            };
+            let nsenter = match &pid {
+                Some(pid) => vec!["nsenter", "--target", pid, "--all", "--"],
+                None => vec![],
+            };
+            connect_exec(&nsenter, exec.path(), exec.argv(), endpoint)
+        }
+        Transport::Launchd(launchd) => {
+            let path = crate::discover::launchd_socket(&ProcessEnvironment, launchd.env())?;
+            connect_unix(&UnixAddrKind::Path(path.into()), endpoint)
+        }
+        Transport::Autolaunch(_) => connect_autolaunch(options, endpoint),
+        t => Err(Error::Other(format!("`{t}:` addresses can't be probed"))),
+    }
+}
+
+// Run `path` with its arguments, after the `wrapper` command if any.
+#[cfg(unix)]
+fn connect_exec(
+    wrapper: &[&str],
+    path: &str,
+    argv: &[(usize, Cow<'_, str>)],
+    endpoint: &mut Option<Endpoint>,
+) -> Result<Stream> {
+    use std::{
+        os::{
+            fd::OwnedFd,
+            unix::{net::UnixStream, process::CommandExt},
+        },
+        process::{Command, Stdio},
+    };
+
+    let args = argv
+        .iter()
+        .filter(|(n, _)| *n != 0)
+        .map(|(_, arg)| arg.as_ref());
+    let line: Vec<&str> = wrapper.iter().copied().chain([path]).chain(args).collect();
+    *endpoint = Some(Endpoint::Exec(crate::shell::join_command_line(
+        line.iter().copied(),
+    )));
+
+    // like libdbus, both standard input and output are one end of a socket pair
+    let (stream, child_end) = UnixStream::pair()?;
+    let mut command = Command::new(line[0]);
+    command
+        .args(&line[1..])
+        .stdin(Stdio::from(OwnedFd::from(child_end.try_clone()?)))
+        .stdout(Stdio::from(OwnedFd::from(child_end)));
+    // `argv0` is lost through a wrapper
+    if let (Some((_, arg0)), []) = (argv.iter().find(|(n, _)| *n == 0), wrapper) {
+        command.arg0(arg0.as_ref());
+    }
+    let child = command.spawn()?;
+
+    Ok(Stream::Exec(stream, Arc::new(ExecChild(child))))
+}
+
+#[cfg(not(unix))]
+fn connect_exec(
+    _wrapper: &[&str],
+    _path: &str,
+    _argv: &[(usize, Cow<'_, str>)],
+    _endpoint: &mut Option<Endpoint>,
+) -> Result<Stream> {
+    Err(Error::Other(
+        "exec addresses can't be probed on this platform".into(),
+    ))
+}
+
+// Connect to the first working address of an autolaunched session bus. On macOS, the session bus
+// is started by launchd instead.
+fn connect_autolaunch(options: &ProbeOptions, endpoint: &mut Option<Endpoint>) -> Result<Stream> {
+    if !options.autolaunch {
+        return Err(Error::Other("autolaunch is disabled".into()));
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let path = crate::discover::launchd_socket(
+            &ProcessEnvironment,
+            "DBUS_LAUNCHD_SESSION_BUS_SOCKET",
+        )?;
+        connect_unix(&UnixAddrKind::Path(path.into()), endpoint)
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        // the launched bus is not under the root directory, and doesn't autolaunch again
+        let options = ProbeOptions {
+            root: None,
+            autolaunch: false,
+            ..options.clone()
+        };
+        let stream = crate::discover::autolaunch(&ProcessEnvironment)?.connect_with(&options)?;
+        *endpoint = stream.endpoint;
+
+        Ok(stream.stream)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = endpoint;
+        Err(Error::Other(
+            "`autolaunch:` addresses can't be probed on this platform".into(),
+        ))
+    }
+}
+
+#[cfg(unix)]
+fn connect_unix(kind: &UnixAddrKind<'_>, endpoint: &mut Option<Endpoint>) -> Result<Stream> {
+    use std::os::unix::net::UnixStream;
+
+    match kind {
+        UnixAddrKind::Path(path) => {
+            let path = PathBuf::from(&**path);
+            *endpoint = Some(Endpoint::Unix(path.clone()));
+            Ok(Stream::Unix(UnixStream::connect(path)?))
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        UnixAddrKind::Abstract(name) => {
+            #[cfg(target_os = "android")]
+            use std::os::android::net::SocketAddrExt;
+            #[cfg(target_os = "linux")]
+            use std::os::linux::net::SocketAddrExt;
+            use std::os::unix::net::SocketAddr;
+
+            *endpoint = Some(Endpoint::Abstract(name.to_vec()));
+            let addr = SocketAddr::from_abstract_name(name)?;
+            Ok(Stream::Unix(UnixStream::connect_addr(&addr)?))
+        }
+        _ => Err(Error::Other(
+            "only `path` and `abstract` `unix:` addresses can be probed".into(),
+        )),
+    }
+}
+
+#[cfg(not(unix))]
+fn connect_unix(_kind: &UnixAddrKind<'_>, _endpoint: &mut Option<Endpoint>) -> Result<Stream> {
+    Err(Error::Other(
+        "`unix:` addresses can't be probed on this platform".into(),
+    ))
+}
+
+fn connect_tcp(
+    host: Option<&str>,
+    port: Option<u16>,
+    family: Option<TcpFamily>,
+    timeout: Duration,
+    endpoint: &mut Option<Endpoint>,
+) -> Result<TcpStream> {
+    let port = port.ok_or_else(|| Error::MissingKey("port".into()))?;
+    let host = host.unwrap_or("localhost");
+    // the resolution time counts, even though it can't be interrupted
+    let deadline = Instant::now() + timeout;
+
+    let mut last_err = None;
+    for addr in (host, port).to_socket_addrs()? {
+        match family {
+            Some(TcpFamily::IPv4) if !addr.is_ipv4() => continue,
+            Some(TcpFamily::IPv6) if !addr.is_ipv6() => continue,
+            _ => {}
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            last_err = Some(io::Error::from(io::ErrorKind::TimedOut));
+            break;
+        }
+        *endpoint = Some(Endpoint::Tcp(addr));
+        match TcpStream::connect_timeout(&addr, left) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    match last_err {
+        Some(e) => Err(e.into()),
+        None => Err(Error::Other(format!("`{host}` has no matching address"))),
+    }
+}
+
+fn connect_nonce_tcp(
+    nonce_tcp: &NonceTcp<'_>,
+    timeout: Duration,
+    endpoint: &mut Option<Endpoint>,
+) -> Result<Stream> {
+    let noncefile = nonce_tcp
+        .noncefile()
+        .ok_or_else(|| Error::MissingKey("noncefile".into()))?;
+    let nonce = fs::read(noncefile)?;
+    let mut stream = connect_tcp(
+        nonce_tcp.host(),
+        nonce_tcp.port(),
+        nonce_tcp.family(),
+        timeout,
+        endpoint,
+    )?;
+    stream.set_write_timeout(Some(timeout))?;
+    stream.write_all(&nonce)?;
+
+    Ok(Stream::Tcp(stream))
+}
+
+// Send the NUL byte and an empty `AUTH` command, the server should reject it with the list of
+// supported mechanisms.
+fn auth(stream: &mut dyn ReadWrite) -> Result<Vec<String>> {
+    stream.write_all(b"\0AUTH\r\n")?;
+
+    let mut line = Vec::new();
+    let mut byte = [0];
+    while !line.ends_with(b"\r\n") {
+        if stream.read(&mut byte)? == 0 {
+            return Err(Error::Other("connection closed by the server".into()));
+        }
+        line.push(byte[0]);
+        if line.len() > 1024 {
+            return Err(Error::Other("server answer is too long".into()));
+        }
+    }
+    let line = String::from_utf8_lossy(&line[..line.len() - 2]);
+
+    match line.strip_prefix("REJECTED") {
+        Some(mechs) if mechs.is_empty() || mechs.starts_with(' ') => {
+            Ok(mechs.split_whitespace().map(str::to_owned).collect())
+        }
+        _ => Err(Error::Other(format!(
+            "unexpected answer from the server: `{line}`"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread};
+
+    use super::*;
+    use crate::DBusAddrList;
+
+    // Accept a connection, and answer the `AUTH` command.
+    fn serve<S: Read + Write>(mut stream: S, answer: &[u8]) -> Vec<u8> {
+        let mut received = Vec::new();
+        let mut byte = [0];
+        while !received.ends_with(b"\r\n") && stream.read(&mut byte).unwrap() == 1 {
+            received.push(byte[0]);
+        }
+        stream.write_all(answer).unwrap();
+        received
+    }
+
+    #[test]
+    fn tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream, b"REJECTED EXTERNAL ANONYMOUS\r\n")
+        });
+
+        let addr = format!("tcp:host=127.0.0.1,port={port}");
+        let report = DBusAddr::try_from(addr.as_str()).unwrap().probe();
+        assert_eq!(report.error(), None);
+        assert_eq!(
+            report.endpoint(),
+            Some(&Endpoint::Tcp(([127, 0, 0, 1], port).into()))
+        );
+        assert!(report.latency().is_some());
+        assert_eq!(
+            report.mechanisms().unwrap(),
+            &["EXTERNAL".to_string(), "ANONYMOUS".to_string()]
+        );
+        assert_eq!(server.join().unwrap(), b"\0AUTH\r\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix() {
+        use std::os::unix::net::UnixListener;
+
+        let dir = crate::tests::TmpDir::new("probe");
+        let path = dir.path().join("bus");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream, b"ERROR\r\n")
+        });
+
+        let addr = format!("unix:path={}", path.display());
+        let addr = DBusAddr::try_from(addr.as_str()).unwrap();
+        let report = addr.probe();
+        assert_eq!(report.endpoint(), Some(&Endpoint::Unix(path.clone())));
+        assert_eq!(
+            report.error(),
+            Some(&Error::Other(
+                "unexpected answer from the server: `ERROR`".into()
+            ))
+        );
+        server.join().unwrap();
+
+        // without auth, only the connection is checked
+        let listener = UnixListener::bind(dir.path().join("bus2")).unwrap();
+        let addr = format!("unix:path={}", dir.path().join("bus2").display());
+        let report = DBusAddr::try_from(addr.as_str())
+            .unwrap()
+            .probe_with(&ProbeOptions::new().auth(false));
+        assert!(report.is_ok());
+        assert_eq!(report.mechanisms(), None);
+        drop(listener);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn connect() {
+        use std::os::unix::net::UnixListener;
+
+        let dir = crate::tests::TmpDir::new("connect");
+        let path = dir.path().join("bus");
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream, b"REJECTED EXTERNAL\r\n")
+        });
+
+        // the first working address wins
+        let addr = format!(
+            "unix:path={}/missing;systemd:;unix:path={}",
+            dir.path().display(),
+            path.display()
+        );
+        let mut stream = DBusAddrList::try_from(addr.as_str())
+            .unwrap()
+            .connect()
+            .unwrap();
+        assert_eq!(stream.endpoint(), Some(&Endpoint::Unix(path)));
+        stream
+            .try_clone()
+            .unwrap()
+            .write_all(b"\0AUTH\r\n")
+            .unwrap();
+        let mut answer = String::new();
+        stream.read_to_string(&mut answer).unwrap();
+        assert_eq!(answer, "REJECTED EXTERNAL\r\n");
+        assert_eq!(server.join().unwrap(), b"\0AUTH\r\n");
+
+        assert_eq!(
+            DBusAddrList::try_from("systemd:")
+                .unwrap()
+                .connect()
+                .unwrap_err(),
+            Error::Other("`systemd:` addresses can't be probed".into())
+        );
+    }
+
+    #[test]
+    fn exec() {
+        use crate::{transport::Unixexec, OwnedDBusAddr};
+
+        let script = r#"sh -c 'head -c 7 >/dev/null; printf "REJECTED EXTERNAL\r\n"'"#;
+        let exec = Unixexec::from_command_line(script).unwrap();
+        let addr = OwnedDBusAddr::from(Transport::Unixexec(exec));
+        let report = DBusAddr::try_from(addr.to_string()).unwrap().probe();
+        assert!(report.is_ok(), "{:?}", report.error());
+        assert_eq!(report.mechanisms().unwrap(), ["EXTERNAL".to_string()]);
+        assert_eq!(report.endpoint(), Some(&Endpoint::Exec(script.into())));
+
+        let mut stream = DBusAddr::try_from("unixexec:path=cat")
+            .unwrap()
+            .connect()
+            .unwrap();
+        stream.try_clone().unwrap().write_all(b"hello").unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        let mut echo = String::new();
+        stream.read_to_string(&mut echo).unwrap();
+        assert_eq!(echo, "hello");
+
+        assert!(DBusAddr::try_from("unixexec:path=/nonexistent")
+            .unwrap()
+            .connect()
+            .is_err());
+    }
+
+    #[test]
+    fn launchd_autolaunch() {
+        assert_eq!(
+            DBusAddr::try_from("launchd:env=DBUS_ADDR_TEST_UNSET")
+                .unwrap()
+                .connect()
+                .unwrap_err(),
+            Error::Other("`DBUS_ADDR_TEST_UNSET` is not set".into())
+        );
+        assert_eq!(
+            DBusAddr::try_from("autolaunch:")
+                .unwrap()
+                .connect()
+                .unwrap_err(),
+            Error::Other("autolaunch is disabled".into())
+        );
+    }
+
+    #[test]
+    fn list() {
+        // a free port, most likely
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let addr = format!("tcp:host=127.0.0.1,port={port};systemd:;tcp:port=abc;tcp:");
+        let list = DBusAddrList::try_from(addr.as_str()).unwrap();
+        let reports = list.probe();
+        assert_eq!(reports.len(), 4);
+        assert!(matches!(reports[0].error(), Some(Error::Io(_))));
+        assert_eq!(reports[0].latency(), None);
+        assert_eq!(
+            reports[1].error(),
+            Some(&Error::Other("`systemd:` addresses can't be probed".into()))
+        );
+        assert_eq!(reports[2].address(), "tcp:port=abc");
+        assert_eq!(
+            reports[2].error(),
+            Some(&Error::InvalidValue("port".into()))
+        );
+        assert_eq!(reports[3].error(), Some(&Error::MissingKey("port".into())));
+    }
+}
@@ -0,0 +1,273 @@
+//! [`Arbitrary`] implementations and strategies, generating well-formed addresses.
+
+use alloc::{borrow::Cow, string::String, vec, vec::Vec};
+
+use ::proptest::{
+    arbitrary::{any, Arbitrary},
+    collection::{btree_map, vec},
+    option, prop_oneof,
+    strategy::{BoxedStrategy, Just, Strategy},
+};
+
+use super::{
+    transport::{
+        Autolaunch, AutolaunchScope, Launchd, MachineExec, MachineRef, MachineUnix, NonceTcp,
+        Systemd, Tcp, TcpFamily, Transport, Unix, UnixAddrKind, Unixexec, Vsock, KNOWN_TRANSPORTS,
+        TRANSPORT_NAME_CHARS,
+    },
+    DBusAddrList, Guid, OwnedDBusAddr,
+};
+
+fn path() -> impl Strategy<Value = Cow<'static, str>> {
+    any::<String>().prop_map(Cow::Owned)
+}
+
+fn string() -> impl Strategy<Value = Cow<'static, str>> {
+    any::<String>().prop_map(Cow::Owned)
+}
+
+/// A strategy for transport names unknown to the parser.
+pub fn other_transport_name() -> impl Strategy<Value = String> {
+    vec(::proptest::sample::select(TRANSPORT_NAME_CHARS), 1..16).prop_map(|chars| {
+        let mut name: String = chars.into_iter().map(char::from).collect();
+        while KNOWN_TRANSPORTS.contains(&name.as_str()) {
+            name.insert_str(0, "x-");
+        }
+
+        name
+    })
+}
+
+/// A strategy for non-empty address lists, of up to `max` addresses.
+pub fn dbus_addr_list(max: usize) -> impl Strategy<Value = DBusAddrList<'static>> {
+    vec(any::<OwnedDBusAddr>(), 1..=max.max(1)).prop_map(|addrs| DBusAddrList::from_addrs(&addrs))
+}
+
+impl Arbitrary for Guid {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<[u8; 16]>().prop_map(Guid::new).boxed()
+    }
+}
+
+impl Arbitrary for TcpFamily {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![Just(TcpFamily::IPv4), Just(TcpFamily::IPv6)].boxed()
+    }
+}
+
+impl Arbitrary for UnixAddrKind<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            path().prop_map(UnixAddrKind::Path),
+            path().prop_map(UnixAddrKind::Dir),
+            path().prop_map(UnixAddrKind::Tmpdir),
+            any::<Vec<u8>>().prop_map(|a| UnixAddrKind::Abstract(Cow::Owned(a))),
+            Just(UnixAddrKind::Runtime),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Unix<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<UnixAddrKind<'static>>().prop_map(Unix::new).boxed()
+    }
+}
+
+impl Arbitrary for Launchd<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        string().prop_map(Launchd::new).boxed()
+    }
+}
+
+impl Arbitrary for Systemd<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        Just(()).prop_map(|_| Systemd::new()).boxed()
+    }
+}
+
+impl Arbitrary for Tcp<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            option::of(string()),
+            option::of(string()),
+            any::<Option<u16>>(),
+            any::<Option<TcpFamily>>(),
+        )
+            .prop_map(|(host, bind, port, family)| Tcp::new(host, bind, port, family))
+            .boxed()
+    }
+}
+
+impl Arbitrary for NonceTcp<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (
+            option::of(string()),
+            option::of(string()),
+            any::<Option<u16>>(),
+            any::<Option<TcpFamily>>(),
+            option::of(path()),
+        )
+            .prop_map(|(host, bind, port, family, noncefile)| {
+                NonceTcp::new(host, bind, port, family, noncefile)
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Unixexec<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        // unique and sorted indexes, as when parsed
+        (path(), btree_map(any::<usize>(), string(), 0..8))
+            .prop_map(|(path, argv)| Unixexec::new(path, argv.into_iter().collect()))
+            .boxed()
+    }
+}
+
+impl Arbitrary for AutolaunchScope<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        // the keywords are mapped to the other variants, as when parsed
+        prop_oneof![
+            Just(AutolaunchScope::InstallPath),
+            Just(AutolaunchScope::User),
+            string().prop_map(|s| AutolaunchScope::try_from(s).unwrap_or(AutolaunchScope::User)),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for Autolaunch<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<Option<AutolaunchScope<'static>>>()
+            .prop_map(Autolaunch::new)
+            .boxed()
+    }
+}
+
+impl Arbitrary for Vsock<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        any::<(Option<u32>, Option<u32>)>()
+            .prop_map(|(cid, port)| Vsock::new(cid, port))
+            .boxed()
+    }
+}
+
+impl Arbitrary for MachineRef<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            string().prop_map(MachineRef::Name),
+            any::<u32>().prop_map(MachineRef::Pid),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for MachineUnix<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<MachineRef<'static>>(), option::of(path()))
+            .prop_map(|(machine, path)| MachineUnix::new(machine, path))
+            .boxed()
+    }
+}
+
+impl Arbitrary for MachineExec<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        // unique and sorted indexes, as when parsed
+        (
+            any::<MachineRef<'static>>(),
+            path(),
+            btree_map(any::<usize>(), string(), 0..8),
+        )
+            .prop_map(|(machine, path, argv)| {
+                MachineExec::new(machine, path, argv.into_iter().collect())
+            })
+            .boxed()
+    }
+}
+
+impl Arbitrary for Transport<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![
+            any::<Unix<'static>>().prop_map(Transport::Unix),
+            any::<Launchd<'static>>().prop_map(Transport::Launchd),
+            any::<Systemd<'static>>().prop_map(Transport::Systemd),
+            any::<Tcp<'static>>().prop_map(Transport::Tcp),
+            any::<NonceTcp<'static>>().prop_map(Transport::NonceTcp),
+            any::<Unixexec<'static>>().prop_map(Transport::Unixexec),
+            any::<Autolaunch<'static>>().prop_map(Transport::Autolaunch),
+            any::<Vsock<'static>>().prop_map(Transport::Vsock),
+            any::<MachineUnix<'static>>().prop_map(Transport::MachineUnix),
+            any::<MachineExec<'static>>().prop_map(Transport::MachineExec),
+            other_transport_name().prop_map(|name| Transport::Other(Cow::Owned(name))),
+        ]
+        .boxed()
+    }
+}
+
+impl Arbitrary for OwnedDBusAddr {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        (any::<Transport<'static>>(), any::<Option<Guid>>())
+            .prop_map(|(transport, guid)| OwnedDBusAddr::from_parts(transport, guid))
+            .boxed()
+    }
+}
+
+impl Arbitrary for DBusAddrList<'static> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        dbus_addr_list(4).boxed()
+    }
+}
@@ -0,0 +1,254 @@
+//! Unix socket paths inside another root directory, such as a container.
+
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use super::{
+    percent::path_value,
+    transport::{Transport, Unix, UnixAddrKind},
+    DBusAddr, Error, OwnedDBusAddr, Result,
+};
+
+// Like `MAXSYMLINKS` on Linux.
+const MAX_SYMLINKS: usize = 40;
+
+/// A root directory, under which `unix:` socket paths are resolved.
+///
+/// A bus address inside a container, such as `unix:path=/run/dbus/system_bus_socket`, refers to the
+/// container filesystem. `RootDir` resolves the `path` and `dir` values of such addresses from the
+/// host, like sd-bus machine-scoped addresses:
+///
+/// - absolute and relative paths are both relative to the root directory.
+/// - absolute symlinks are followed from the root directory.
+/// - `..` components that would leave the root directory are refused.
+///
+/// The resolution is done once: a path changed in the meantime may still lead outside the root.
+///
+/// Example:
+/// ```no_run
+/// use dbus_addr::{DBusAddr, ProbeOptions, RootDir};
+///
+/// let addr = DBusAddr::try_from("unix:path=/run/dbus/system_bus_socket").unwrap();
+/// let root = RootDir::new("/var/lib/machines/debian");
+/// println!("{}", root.resolve_addr(&addr).unwrap());
+///
+/// let report = addr.probe_with(&ProbeOptions::new().root(root));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootDir {
+    path: PathBuf,
+}
+
+impl RootDir {
+    /// The given root directory.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// The root directory of a process, `/proc/<pid>/root`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn for_pid(pid: u32) -> Self {
+        Self::new(format!("/proc/{pid}/root"))
+    }
+
+    /// The root directory location.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Resolve a path under the root directory.
+    ///
+    /// Symlinks are followed, and missing components are kept as is.
+    pub fn resolve<P: AsRef<Path>>(&self, path: P) -> Result<PathBuf> {
+        let path = path.as_ref();
+        let escape = || Error::Other(format!("`{}` escapes the root directory", path.display()));
+
+        let mut todo = components(path);
+        let mut resolved = PathBuf::new();
+        let mut symlinks = 0;
+        while let Some(comp) = todo.pop_front() {
+            if comp == ".." {
+                if !resolved.pop() {
+                    return Err(escape());
+                }
+                continue;
+            }
+            let next = resolved.join(&comp);
+            let full = self.path.join(&next);
+            match fs::symlink_metadata(&full) {
+                Ok(meta) if meta.file_type().is_symlink() => {
+                    symlinks += 1;
+                    if symlinks > MAX_SYMLINKS {
+                        return Err(Error::Other(format!(
+                            "too many symlinks in `{}`",
+                            path.display()
+                        )));
+                    }
+                    let target = fs::read_link(&full)?;
+                    if target.has_root() {
+                        resolved.clear();
+                    }
+                    for comp in components(&target).into_iter().rev() {
+                        todo.push_front(comp);
+                    }
+                }
+                Ok(_) => resolved = next,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => resolved = next,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(self.path.join(resolved))
+    }
+
+    /// Resolve the `path` or `dir` of a `unix:` address under the root directory.
+    ///
+    /// Other addresses are returned unchanged.
+    pub fn resolve_addr(&self, addr: &DBusAddr<'_>) -> Result<OwnedDBusAddr> {
+        let transport = self.resolve_transport(&addr.transport()?)?;
+
+        Ok(OwnedDBusAddr::from_parts(transport, addr.guid()?))
+    }
+
+    pub(crate) fn resolve_transport(
+        &self,
+        transport: &Transport<'_>,
+    ) -> Result<Transport<'static>> {
+        let kind = match transport {
+            Transport::Unix(unix) => match unix.kind() {
+                UnixAddrKind::Path(p) => UnixAddrKind::Path(path_value(self.resolve(&**p)?)?),
+                UnixAddrKind::Dir(p) => UnixAddrKind::Dir(path_value(self.resolve(&**p)?)?),
+                _ => return Ok(transport.clone().into_owned()),
+            },
+            _ => return Ok(transport.clone().into_owned()),
+        };
+
+        Ok(Transport::Unix(Unix::new(kind)))
+    }
+}
+
+// The normal and `..` components of a path.
+fn components(path: &Path) -> VecDeque<OsString> {
+    path.components()
+        .filter_map(|comp| match comp {
+            Component::Normal(s) => Some(s.to_owned()),
+            Component::ParentDir => Some("..".into()),
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => None,
+        })
+        .collect()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::{fs::symlink, net::UnixListener};
+
+    use super::*;
+    use crate::{tests::TmpDir, Endpoint, ProbeOptions};
+
+    #[test]
+    fn resolve() {
+        let dir = TmpDir::new("root");
+        let root = RootDir::new(dir.path());
+        let r = dir.path();
+        fs::create_dir_all(r.join("run/dbus")).unwrap();
+        fs::create_dir_all(r.join("var")).unwrap();
+        symlink("/run", r.join("var/run")).unwrap();
+        symlink("../run/dbus", r.join("var/dbus")).unwrap();
+        symlink("../../..", r.join("run/dbus/up")).unwrap();
+        symlink("loop", r.join("loop")).unwrap();
+
+        assert_eq!(
+            root.resolve("/run/dbus/bus").unwrap(),
+            r.join("run/dbus/bus")
+        );
+        assert_eq!(root.resolve("run/./dbus").unwrap(), r.join("run/dbus"));
+        assert_eq!(root.resolve("/var/run/dbus").unwrap(), r.join("run/dbus"));
+        assert_eq!(
+            root.resolve("/var/dbus/bus").unwrap(),
+            r.join("run/dbus/bus")
+        );
+        assert_eq!(root.resolve("/missing/../run").unwrap(), r.join("run"));
+        assert_eq!(root.resolve("/").unwrap(), r);
+
+        assert_eq!(
+            root.resolve("/run/../../etc").unwrap_err(),
+            Error::Other("`/run/../../etc` escapes the root directory".into())
+        );
+        assert!(root.resolve("/run/dbus/up/etc").is_err());
+        assert_eq!(
+            root.resolve("/loop").unwrap_err(),
+            Error::Other("too many symlinks in `/loop`".into())
+        );
+    }
+
+    #[test]
+    fn resolve_addr() {
+        let dir = TmpDir::new("root addr");
+        let root = RootDir::new(dir.path());
+        fs::create_dir(dir.path().join("run")).unwrap();
+        symlink("/run", dir.path().join("tmp")).unwrap();
+
+        let addr =
+            DBusAddr::try_from("unix:path=/tmp/bus,guid=0123456789abcdef0123456789abcdef").unwrap();
+        let resolved = root.resolve_addr(&addr).unwrap();
+        let Transport::Unix(unix) = resolved.transport() else {
+            panic!();
+        };
+        assert_eq!(
+            unix.kind(),
+            &UnixAddrKind::Path(dir.path().join("run/bus").to_str().unwrap().into())
+        );
+        assert_eq!(resolved.guid(), addr.guid().unwrap().as_ref());
+
+        let addr = DBusAddr::try_from("unix:dir=/tmp").unwrap();
+        let Transport::Unix(unix) = root.resolve_addr(&addr).unwrap().transport().clone() else {
+            panic!();
+        };
+        assert_eq!(
+            unix.kind(),
+            &UnixAddrKind::Dir(dir.path().join("run").to_str().unwrap().into())
+        );
+
+        for addr in ["unix:abstract=/tmp/bus", "tcp:host=localhost,port=1"] {
+            let addr = DBusAddr::try_from(addr).unwrap();
+            assert_eq!(root.resolve_addr(&addr).unwrap().to_string(), addr.as_str());
+        }
+    }
+
+    #[test]
+    fn probe() {
+        let dir = TmpDir::new("root probe");
+        let _listener = UnixListener::bind(dir.path().join("bus")).unwrap();
+
+        let addr = DBusAddr::try_from("unix:path=/bus").unwrap();
+        let report = addr.probe_with(
+            &ProbeOptions::new()
+                .auth(false)
+                .root(RootDir::new(dir.path())),
+        );
+        assert!(report.is_ok());
+        assert_eq!(
+            report.endpoint(),
+            Some(&Endpoint::Unix(dir.path().join("bus")))
+        );
+
+        let addr = DBusAddr::try_from("unix:path=/../bus").unwrap();
+        let report = addr.probe_with(&ProbeOptions::new().root(RootDir::new(dir.path())));
+        assert!(report.error().is_some());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn for_pid() {
+        let root = RootDir::for_pid(std::process::id());
+        let dir = TmpDir::new("root pid");
+        assert_eq!(
+            root.resolve(dir.path()).unwrap(),
+            root.path().join(dir.path().strip_prefix("/").unwrap())
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Session buses recorded by `dbus-launch` in `~/.dbus/session-bus`.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::{DBusAddrList, Error, Guid, LaunchedBus, Result};
+
+/// A `~/.dbus/session-bus/<machine-id>-<display>` file.
+///
+/// `dbus-launch` records the session bus it starts for an X11 display in this file, as
+/// `DBUS_SESSION_BUS_ADDRESS=...` lines. Despite the shell-like look, values are not quoted, so the
+/// file is not parsed as shell syntax.
+///
+/// Example:
+/// ```no_run
+/// use dbus_addr::{machine_id, SessionBusFile};
+///
+/// if let Some(file) = SessionBusFile::for_display(&machine_id().unwrap()).unwrap() {
+///     if let Some(bus) = file.running_bus().unwrap() {
+///         println!("{}", bus.address());
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionBusFile {
+    path: PathBuf,
+}
+
+impl SessionBusFile {
+    /// The session bus file for the given home directory, machine ID and X11 display.
+    pub fn new<P: AsRef<Path>>(home: P, machine_id: &Guid, display: &str) -> Self {
+        let display = display_file_name(display);
+        let path = home
+            .as_ref()
+            .join(".dbus")
+            .join("session-bus")
+            .join(format!("{machine_id}-{display}"));
+
+        Self { path }
+    }
+
+    /// The session bus file for the given machine ID, `$HOME` and `$DISPLAY`.
+    ///
+    /// Returns `None` if either environment variable is not set.
+    pub fn for_display(machine_id: &Guid) -> Result<Option<Self>> {
+        let (Ok(home), Ok(display)) = (env::var("HOME"), env::var("DISPLAY")) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self::new(home, machine_id, &display)))
+    }
+
+    /// The file location.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read and parse the file.
+    pub fn read(&self) -> Result<LaunchedBus> {
+        parse(&fs::read_to_string(&self.path)?)
+    }
+
+    /// The recorded session bus, if the file exists and its bus daemon is still alive.
+    pub fn running_bus(&self) -> Result<Option<LaunchedBus>> {
+        let bus = match self.read() {
+            Ok(bus) => bus,
+            Err(Error::Io(_)) if !self.path.exists() => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        match bus.pid() {
+            Some(pid) if !pid_alive(pid) => Ok(None),
+            _ => Ok(Some(bus)),
+        }
+    }
+}
+
+// The display part of the file name, as computed by `dbus-launch`: the screen number and a local
+// host name are dropped, and `/` are replaced with `_`.
+fn display_file_name(display: &str) -> String {
+    let mut display = display;
+    if let Some(col) = display.rfind(':') {
+        if let Some(dot) = display[col..].find('.') {
+            display = &display[..col + dot];
+        }
+    }
+    for local in ["localhost.localdomain:", "localhost:"] {
+        if let Some(d) = display.strip_prefix(local) {
+            display = d;
+        }
+    }
+    let display = display.strip_prefix(':').unwrap_or(display);
+
+    display.replace('/', "_")
+}
+
+// `KEY=value` lines, other lines are ignored.
+fn parse(content: &str) -> Result<LaunchedBus> {
+    let mut address = None;
+    let mut pid = None;
+    let mut window_id = None;
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, val)) = line.split_once('=') else {
+            continue;
+        };
+        let val = unquote(val.trim_end_matches(';'));
+        match key {
+            "DBUS_SESSION_BUS_ADDRESS" => address = Some(val.to_owned()),
+            "DBUS_SESSION_BUS_PID" => {
+                pid = Some(val.parse().map_err(|_| Error::InvalidValue(key.into()))?);
+            }
+            "DBUS_SESSION_BUS_WINDOWID" => {
+                window_id = Some(val.parse().map_err(|_| Error::InvalidValue(key.into()))?);
+            }
+            _ => continue,
+        }
+    }
+
+    let address = address.ok_or_else(|| Error::MissingKey("DBUS_SESSION_BUS_ADDRESS".into()))?;
+
+    Ok(LaunchedBus::new(
+        DBusAddrList::try_from(address)?,
+        pid,
+        window_id,
+    ))
+}
+
+fn unquote(val: &str) -> &str {
+    for q in ['\'', '"'] {
+        if let Some(v) = val.strip_prefix(q).and_then(|v| v.strip_suffix(q)) {
+            return v;
+        }
+    }
+
+    val
+}
+
+// Whether the process exists, even if we are not allowed to signal it.
+pub(crate) fn pid_alive(pid: u32) -> bool {
+    #[link(name = "c")]
+    extern "C" {
+        fn kill(pid: i32, sig: i32) -> i32;
+    }
+
+    let Ok(pid) = i32::try_from(pid) else {
+        return false;
+    };
+    if pid <= 0 {
+        return false;
+    }
+    if unsafe { kill(pid, 0) } == 0 {
+        return true;
+    }
+
+    io::Error::last_os_error().kind() == io::ErrorKind::PermissionDenied
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "\
+# This file allows processes on the machine with id 9406e28972c595c590766c9564ce623f using
+# display :0 to find the running session bus with the below address.
+# If the DBUS_SESSION_BUS_ADDRESS environment variable is set, it will
+# be used rather than this file.
+# See \"man dbus-launch\" for more details.
+DBUS_SESSION_BUS_ADDRESS=unix:abstract=/tmp/dbus-ZPnKsu,guid=9406e28972c595c590766c9564ce623f
+DBUS_SESSION_BUS_PID=@PID@
+DBUS_SESSION_BUS_WINDOWID=16777217
+";
+
+    #[test]
+    fn file_name() {
+        let id = Guid::try_from("9406e28972c595c590766c9564ce623f").unwrap();
+        let file = SessionBusFile::new("/home/user", &id, ":0.0");
+        assert_eq!(
+            file.path(),
+            Path::new("/home/user/.dbus/session-bus/9406e28972c595c590766c9564ce623f-0")
+        );
+
+        assert_eq!(display_file_name(":1"), "1");
+        assert_eq!(display_file_name("localhost:10.0"), "10");
+        assert_eq!(display_file_name("remote:2.1"), "remote:2");
+        assert_eq!(
+            display_file_name("/tmp/launch-x/org.xquartz:0"),
+            "_tmp_launch-x_org.xquartz:0"
+        );
+    }
+
+    #[test]
+    fn running_bus() {
+        let dir = crate::tests::TmpDir::new("session-bus");
+        let id = Guid::try_from("9406e28972c595c590766c9564ce623f").unwrap();
+        let file = SessionBusFile::new(dir.path(), &id, ":0");
+        assert_eq!(file.running_bus().unwrap(), None);
+
+        fs::create_dir_all(file.path().parent().unwrap()).unwrap();
+        let content = FILE.replace("@PID@", &std::process::id().to_string());
+        fs::write(file.path(), content).unwrap();
+        let bus = file.running_bus().unwrap().unwrap();
+        assert_eq!(
+            bus.address().to_string(),
+            "unix:abstract=/tmp/dbus-ZPnKsu,guid=9406e28972c595c590766c9564ce623f"
+        );
+        assert_eq!(bus.pid(), Some(std::process::id()));
+        assert_eq!(bus.window_id(), Some(16777217));
+
+        // the daemon is gone
+        fs::write(file.path(), FILE.replace("@PID@", &i32::MAX.to_string())).unwrap();
+        assert_eq!(file.running_bus().unwrap(), None);
+
+        fs::write(file.path(), "DBUS_SESSION_BUS_PID=1\n").unwrap();
+        assert_eq!(
+            file.running_bus().unwrap_err(),
+            Error::MissingKey("DBUS_SESSION_BUS_ADDRESS".into())
+        );
+    }
+
+    #[test]
+    fn parse_quoted() {
+        let bus =
+            parse("DBUS_SESSION_BUS_ADDRESS='unix:path=/tmp/bus';\nDBUS_SESSION_BUS_PID=\"12\"")
+                .unwrap();
+        assert_eq!(bus.address().to_string(), "unix:path=/tmp/bus");
+        assert_eq!(bus.pid(), Some(12));
+        assert_eq!(bus.window_id(), None);
+    }
+
+    #[test]
+    fn parse_list() {
+        // dbus-launch doesn't quote the address, `;` is part of it
+        let bus = parse(
+            "DBUS_SESSION_BUS_ADDRESS=unix:path=/a;tcp:host=h,port=1\n\
+             some other line\n\
+             DBUS_SESSION_BUS_PID=12\n",
+        )
+        .unwrap();
+        assert_eq!(bus.address().to_string(), "unix:path=/a;tcp:host=h,port=1");
+        assert_eq!(bus.pid(), Some(12));
+    }
+}
@@ -0,0 +1,557 @@
+//! Shell environment snippets, as printed by `dbus-launch` or read from `environment.d`.
+
+use std::{collections::HashMap, ffi::c_long, mem};
+
+use super::{DBusAddrList, Error, Result};
+
+const ADDRESS: &str = "DBUS_SESSION_BUS_ADDRESS";
+const PID: &str = "DBUS_SESSION_BUS_PID";
+const WINDOW_ID: &str = "DBUS_SESSION_BUS_WINDOWID";
+
+/// A session bus started by `dbus-launch`.
+///
+/// It can be written and read in the `dbus-launch` `--sh-syntax`, `--csh-syntax` and
+/// `--binary-syntax` formats, and as a systemd `environment.d` file.
+///
+/// Example:
+/// ```
+/// use dbus_addr::LaunchedBus;
+///
+/// let bus = LaunchedBus::new("unix:path=/tmp/my bus".try_into().unwrap(), Some(42), None);
+/// let sh = bus.to_sh_syntax();
+/// assert_eq!(
+///     sh,
+///     "DBUS_SESSION_BUS_ADDRESS='unix:path=/tmp/my bus';\n\
+///      export DBUS_SESSION_BUS_ADDRESS;\n\
+///      DBUS_SESSION_BUS_PID=42;\n"
+/// );
+/// assert_eq!(LaunchedBus::from_sh_syntax(&sh).unwrap(), bus);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchedBus {
+    address: DBusAddrList<'static>,
+    pid: Option<u32>,
+    window_id: Option<u64>,
+}
+
+impl LaunchedBus {
+    /// Create a bus with its address list, and if known the PID of the bus daemon and its X11
+    /// window ID.
+    pub fn new(address: DBusAddrList<'static>, pid: Option<u32>, window_id: Option<u64>) -> Self {
+        Self {
+            address,
+            pid,
+            window_id,
+        }
+    }
+
+    /// The bus address list.
+    pub fn address(&self) -> &DBusAddrList<'static> {
+        &self.address
+    }
+
+    /// Convert into the bus address list.
+    pub fn into_address(self) -> DBusAddrList<'static> {
+        self.address
+    }
+
+    /// If known, the PID of the bus daemon.
+    pub fn pid(&self) -> Option<u32> {
+        self.pid
+    }
+
+    /// If known, the X11 window ID owned by the bus daemon.
+    pub fn window_id(&self) -> Option<u64> {
+        self.window_id
+    }
+
+    /// Bourne shell assignments, like `dbus-launch --sh-syntax`.
+    pub fn to_sh_syntax(&self) -> String {
+        let mut out = format!(
+            "{ADDRESS}={};\nexport {ADDRESS};\n",
+            sh_quote(&self.address.to_string())
+        );
+        if let Some(pid) = self.pid {
+            out.push_str(&format!("{PID}={pid};\n"));
+        }
+        if let Some(window_id) = self.window_id {
+            out.push_str(&format!("{WINDOW_ID}={window_id};\n"));
+        }
+
+        out
+    }
+
+    /// Parse Bourne shell assignments, as written by [`LaunchedBus::to_sh_syntax`].
+    pub fn from_sh_syntax(s: &str) -> Result<Self> {
+        let mut vars = HashMap::new();
+        for cmd in commands(s, false)? {
+            match cmd.as_slice() {
+                [export, ..] if export == "export" => {}
+                [assign] => match assign.split_once('=') {
+                    Some((key, val)) => {
+                        vars.insert(key.to_owned(), val.to_owned());
+                    }
+                    None => return Err(unexpected(&cmd)),
+                },
+                _ => return Err(unexpected(&cmd)),
+            }
+        }
+
+        Self::from_vars(vars)
+    }
+
+    /// C shell commands, like `dbus-launch --csh-syntax`.
+    pub fn to_csh_syntax(&self) -> String {
+        let mut out = format!(
+            "setenv {ADDRESS} {};\n",
+            csh_quote(&self.address.to_string())
+        );
+        if let Some(pid) = self.pid {
+            out.push_str(&format!("set {PID}={pid};\n"));
+        }
+        if let Some(window_id) = self.window_id {
+            out.push_str(&format!("set {WINDOW_ID}={window_id};\n"));
+        }
+
+        out
+    }
+
+    /// Parse C shell commands, as written by [`LaunchedBus::to_csh_syntax`].
+    pub fn from_csh_syntax(s: &str) -> Result<Self> {
+        let mut vars = HashMap::new();
+        for cmd in commands(s, true)? {
+            let (key, val) = match cmd.as_slice() {
+                [setenv, key, val] if setenv == "setenv" => (key.clone(), val.clone()),
+                [set, assign] if set == "set" => match assign.split_once('=') {
+                    Some((key, val)) => (key.to_owned(), val.to_owned()),
+                    None => return Err(unexpected(&cmd)),
+                },
+                _ => return Err(unexpected(&cmd)),
+            };
+            vars.insert(key, val);
+        }
+
+        Self::from_vars(vars)
+    }
+
+    /// The NUL-terminated address, followed by the native `pid_t` and `long` values of the
+    /// daemon PID and X11 window ID, like `dbus-launch --binary-syntax`.
+    ///
+    /// Unknown values are written as 0.
+    pub fn to_binary_syntax(&self) -> Vec<u8> {
+        let mut out = self.address.to_string().into_bytes();
+        out.push(0);
+        out.extend_from_slice(&(self.pid.unwrap_or(0) as i32).to_ne_bytes());
+        out.extend_from_slice(&(self.window_id.unwrap_or(0) as c_long).to_ne_bytes());
+
+        out
+    }
+
+    /// Parse `dbus-launch --binary-syntax` output.
+    ///
+    /// The PID and window ID are optional, and unknown when 0.
+    pub fn from_binary_syntax(data: &[u8]) -> Result<Self> {
+        let end = data
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| Error::Other("invalid `dbus-launch` output".into()))?;
+        let address = std::str::from_utf8(&data[..end])
+            .map_err(|e| Error::Encoding(format!("{e}")))?
+            .to_owned();
+        if address.is_empty() {
+            return Err(Error::Other("`dbus-launch` returned no address".into()));
+        }
+        let address = DBusAddrList::try_from(address)?;
+
+        let rest = &data[end + 1..];
+        let pid = rest
+            .get(..mem::size_of::<i32>())
+            .map(|b| i32::from_ne_bytes(b.try_into().unwrap()) as u32)
+            .filter(|&pid| pid != 0);
+        let rest = rest.get(mem::size_of::<i32>()..).unwrap_or_default();
+        let window_id = rest
+            .get(..mem::size_of::<c_long>())
+            .map(|b| match b.len() {
+                4 => u32::from_ne_bytes(b.try_into().unwrap()) as u64,
+                _ => u64::from_ne_bytes(b.try_into().unwrap()),
+            })
+            .filter(|&id| id != 0);
+
+        Ok(Self::new(address, pid, window_id))
+    }
+
+    /// A systemd `environment.d` file.
+    ///
+    /// Values are double-quoted when needed, so that `$` is not expanded.
+    pub fn to_environment_d(&self) -> String {
+        let mut out = format!("{ADDRESS}={}\n", env_quote(&self.address.to_string()));
+        if let Some(pid) = self.pid {
+            out.push_str(&format!("{PID}={pid}\n"));
+        }
+        if let Some(window_id) = self.window_id {
+            out.push_str(&format!("{WINDOW_ID}={window_id}\n"));
+        }
+
+        out
+    }
+
+    /// Parse a systemd `environment.d` file.
+    ///
+    /// Other variables are ignored, and `$` expansions are not supported. Unquoted values are
+    /// taken verbatim up to the end of the line, so address lists don't need quotes.
+    pub fn from_environment_d(s: &str) -> Result<Self> {
+        let mut vars = HashMap::new();
+        for line in s.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, val)) = line.split_once('=') else {
+                return Err(Error::Other(format!(
+                    "invalid `environment.d` line `{line}`"
+                )));
+            };
+            let val = env_unquote(val.trim())
+                .ok_or_else(|| Error::Other(format!("invalid `environment.d` line `{line}`")))?;
+            vars.insert(key.trim().to_owned(), val);
+        }
+
+        Self::from_vars(vars)
+    }
+
+    fn from_vars(mut vars: HashMap<String, String>) -> Result<Self> {
+        let address = vars
+            .remove(ADDRESS)
+            .ok_or_else(|| Error::MissingKey(ADDRESS.into()))?;
+        let pid = match vars.get(PID) {
+            Some(pid) => Some(pid.parse().map_err(|_| Error::InvalidValue(PID.into()))?),
+            None => None,
+        };
+        let window_id = match vars.get(WINDOW_ID) {
+            Some(id) => Some(
+                id.parse()
+                    .map_err(|_| Error::InvalidValue(WINDOW_ID.into()))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self::new(DBusAddrList::try_from(address)?, pid, window_id))
+    }
+}
+
+// Single-quote, closing the quotes around `'`.
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+// Like `sh_quote`, but history expansion and newlines must also be escaped in quotes.
+fn csh_quote(s: &str) -> String {
+    format!(
+        "'{}'",
+        s.replace('\'', r"'\''")
+            .replace('!', r"\!")
+            .replace('\n', "\\\n")
+    )
+}
+
+fn env_quote(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_/.:,=%@+*".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        return s.to_owned();
+    }
+
+    let mut out = String::from('"');
+    for c in s.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+
+    out
+}
+
+// An `environment.d` value: verbatim, unless the whole value is single or double-quoted.
+fn env_unquote(s: &str) -> Option<String> {
+    let mut chars = s.chars();
+    let quote = match chars.next() {
+        Some(q @ ('"' | '\'')) => q,
+        _ => return Some(s.to_owned()),
+    };
+
+    let mut out = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c == quote => return chars.next().is_none().then_some(out),
+            '\\' if quote == '"' => match chars.next()? {
+                c @ ('"' | '\\' | '$' | '`') => out.push(c),
+                '\n' => (),
+                c => out.extend(['\\', c]),
+            },
+            c => out.push(c),
+        }
+    }
+
+    // unterminated
+    None
+}
+
+// Quote a word for a POSIX shell, only if needed.
+pub(crate) fn word_quote(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_/.:,=%@+".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        return s.to_owned();
+    }
+
+    sh_quote(s)
+}
+
+// A command line, from its words.
+pub(crate) fn join_command_line<'a, I: IntoIterator<Item = &'a str>>(words: I) -> String {
+    words
+        .into_iter()
+        .map(word_quote)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The words of a single command line.
+pub(crate) fn split_command_line(s: &str) -> Result<Vec<String>> {
+    let mut commands = commands(s, false)?;
+    match commands.len() {
+        0 => Err(Error::Other("empty command line".into())),
+        1 => Ok(commands.remove(0)),
+        _ => Err(Error::Other(format!("`{s}` is not a single command line"))),
+    }
+}
+
+fn unexpected(cmd: &[String]) -> Error {
+    Error::Other(format!("unexpected shell command `{}`", cmd.join(" ")))
+}
+
+// Split commands on `;` and newlines, and commands into unquoted words.
+//
+// This supports the quoting of `sh_quote` and `csh_quote`: single and double quotes,
+// backslash escapes and comments, but no expansion.
+fn commands(s: &str, csh: bool) -> Result<Vec<Vec<String>>> {
+    let mut commands = vec![];
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' | '\n' => {
+                words.extend(word.take());
+                if !words.is_empty() {
+                    commands.push(std::mem::take(&mut words));
+                }
+            }
+            ' ' | '\t' | '\r' => words.extend(word.take()),
+            '#' if word.is_none() => {
+                while chars.peek().is_some_and(|&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            '\\' => match chars.next() {
+                Some('\n') => {}
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => return Err(Error::Other("trailing backslash".into())),
+            },
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') if csh && matches!(chars.peek(), Some('!' | '\n')) => {
+                            word.extend(chars.next())
+                        }
+                        Some(c) => word.push(c),
+                        None => return Err(Error::Other("unterminated quote".into())),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.peek(), Some('"' | '\\' | '$' | '`')) => {
+                            word.extend(chars.next())
+                        }
+                        Some('\\') if chars.peek() == Some(&'\n') => {
+                            chars.next();
+                        }
+                        Some(c) => word.push(c),
+                        None => return Err(Error::Other("unterminated quote".into())),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    if !words.is_empty() {
+        commands.push(words);
+    }
+
+    Ok(commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bus(addr: &str, pid: Option<u32>, window_id: Option<u64>) -> LaunchedBus {
+        LaunchedBus::new(addr.to_owned().try_into().unwrap(), pid, window_id)
+    }
+
+    #[test]
+    fn sh() {
+        let b = bus(
+            "unix:path=/tmp/it's,guid=9406e28972c595c590766c9564ce623f;tcp:port=1",
+            Some(42),
+            Some(7),
+        );
+        let sh = b.to_sh_syntax();
+        assert_eq!(
+            sh,
+            "DBUS_SESSION_BUS_ADDRESS='unix:path=/tmp/it'\\''s,\
+             guid=9406e28972c595c590766c9564ce623f;tcp:port=1';\n\
+             export DBUS_SESSION_BUS_ADDRESS;\n\
+             DBUS_SESSION_BUS_PID=42;\n\
+             DBUS_SESSION_BUS_WINDOWID=7;\n"
+        );
+        assert_eq!(LaunchedBus::from_sh_syntax(&sh).unwrap(), b);
+
+        // as found in `~/.dbus/session-bus` files
+        let b = LaunchedBus::from_sh_syntax(
+            "# comment\nDBUS_SESSION_BUS_ADDRESS=\"unix:abstract=/tmp/\\$x\"\nDBUS_SESSION_BUS_PID=1",
+        )
+        .unwrap();
+        assert_eq!(b.address().to_string(), "unix:abstract=/tmp/$x");
+        assert_eq!(b.pid(), Some(1));
+        assert_eq!(b.window_id(), None);
+
+        assert_eq!(
+            LaunchedBus::from_sh_syntax("export DBUS_SESSION_BUS_ADDRESS;").unwrap_err(),
+            Error::MissingKey("DBUS_SESSION_BUS_ADDRESS".into())
+        );
+        assert!(LaunchedBus::from_sh_syntax("DBUS_SESSION_BUS_ADDRESS='unix:").is_err());
+        assert!(LaunchedBus::from_sh_syntax("rm -rf /").is_err());
+    }
+
+    #[test]
+    fn csh() {
+        let b = bus("unix:path=/tmp/it's!\nhere", Some(42), None);
+        let csh = b.to_csh_syntax();
+        assert_eq!(
+            csh,
+            "setenv DBUS_SESSION_BUS_ADDRESS 'unix:path=/tmp/it'\\''s\\!\\\nhere';\n\
+             set DBUS_SESSION_BUS_PID=42;\n"
+        );
+        assert_eq!(LaunchedBus::from_csh_syntax(&csh).unwrap(), b);
+        assert!(LaunchedBus::from_csh_syntax("DBUS_SESSION_BUS_PID=42").is_err());
+    }
+
+    #[test]
+    fn binary() {
+        let b = bus("unix:path=/tmp/bus", Some(42), Some(7));
+        let data = b.to_binary_syntax();
+        assert_eq!(
+            data.len(),
+            "unix:path=/tmp/bus".len() + 1 + 4 + mem::size_of::<c_long>()
+        );
+        assert_eq!(LaunchedBus::from_binary_syntax(&data).unwrap(), b);
+
+        let b = LaunchedBus::from_binary_syntax(b"unix:path=/tmp/bus\0").unwrap();
+        assert_eq!(b.pid(), None);
+        assert_eq!(b.window_id(), None);
+
+        // unknown values are written, and read, as 0
+        let b = bus("unix:path=/tmp/bus", Some(42), None);
+        assert_eq!(
+            LaunchedBus::from_binary_syntax(&b.to_binary_syntax()).unwrap(),
+            b
+        );
+        let b = bus("unix:path=/tmp/bus", None, None);
+        assert_eq!(
+            LaunchedBus::from_binary_syntax(&b.to_binary_syntax()).unwrap(),
+            b
+        );
+
+        assert!(LaunchedBus::from_binary_syntax(b"unix:path=/tmp/bus").is_err());
+        assert!(LaunchedBus::from_binary_syntax(b"\0").is_err());
+    }
+
+    #[test]
+    fn command_line() {
+        let words = ["ssh", "-xT", "it's", "", "a b", "$HOME", "*"];
+        let line = join_command_line(words);
+        assert_eq!(line, r#"ssh -xT 'it'\''s' '' 'a b' '$HOME' '*'"#);
+        assert_eq!(split_command_line(&line).unwrap(), words);
+
+        assert_eq!(
+            split_command_line(" a \"b c\"  # comment").unwrap(),
+            ["a", "b c"]
+        );
+        assert!(split_command_line("# comment").is_err());
+        assert!(split_command_line("a; b").is_err());
+        assert!(split_command_line("'a").is_err());
+    }
+
+    #[test]
+    fn environment_d() {
+        let b = bus("unix:path=/run/user/1000/bus", None, None);
+        let env = b.to_environment_d();
+        assert_eq!(
+            env,
+            "DBUS_SESSION_BUS_ADDRESS=unix:path=/run/user/1000/bus\n"
+        );
+        assert_eq!(LaunchedBus::from_environment_d(&env).unwrap(), b);
+
+        let b = bus("unix:path=/tmp/$HOME \"bus\"", Some(1), None);
+        let env = b.to_environment_d();
+        assert_eq!(
+            env,
+            "DBUS_SESSION_BUS_ADDRESS=\"unix:path=/tmp/\\$HOME \\\"bus\\\"\"\n\
+             DBUS_SESSION_BUS_PID=1\n"
+        );
+        assert_eq!(
+            LaunchedBus::from_environment_d(&format!("# session\nPATH=/bin\n{env}")).unwrap(),
+            b
+        );
+
+        // `;` has no special meaning
+        let b = bus("unix:path=/a;tcp:host=h,port=1", None, None);
+        assert_eq!(
+            LaunchedBus::from_environment_d(
+                "DBUS_SESSION_BUS_ADDRESS=unix:path=/a;tcp:host=h,port=1"
+            )
+            .unwrap(),
+            b
+        );
+        assert_eq!(
+            LaunchedBus::from_environment_d(&b.to_environment_d()).unwrap(),
+            b
+        );
+
+        // unquoted values are kept verbatim, spaces included
+        assert_eq!(
+            LaunchedBus::from_environment_d("DBUS_SESSION_BUS_ADDRESS = x-my bus:key=1 \n")
+                .unwrap(),
+            bus("x-my bus:key=1", None, None)
+        );
+        assert_eq!(
+            LaunchedBus::from_environment_d("DBUS_SESSION_BUS_ADDRESS='x-$bus:key=\\1'").unwrap(),
+            bus("x-$bus:key=\\1", None, None)
+        );
+
+        assert!(
+            LaunchedBus::from_environment_d("DBUS_SESSION_BUS_ADDRESS=\"unix:path=/a").is_err()
+        );
+        assert!(LaunchedBus::from_environment_d("DBUS_SESSION_BUS_ADDRESS='a' b").is_err());
+        assert!(LaunchedBus::from_environment_d("garbage").is_err());
+    }
+}
@@ -0,0 +1,561 @@
+//! Test utilities, with the `test-util` feature.
+//!
+//! [`MockListener`] listens on an address and records what clients send, so that code consuming
+//! addresses can be tested without a bus daemon.
+//!
+//! Example:
+//! ```
+//! use std::{io::Write, net::TcpStream, time::Duration};
+//!
+//! use dbus_addr::{testing::MockListener, transport::Transport, DBusAddr};
+//!
+//! let listener =
+//!     MockListener::bind(&DBusAddr::try_from("tcp:host=127.0.0.1,port=0").unwrap()).unwrap();
+//! let Transport::Tcp(tcp) = listener.addr().transport() else {
+//!     panic!()
+//! };
+//! let mut stream = TcpStream::connect((tcp.host().unwrap(), tcp.port().unwrap())).unwrap();
+//! stream.write_all(b"hello").unwrap();
+//! drop(stream);
+//!
+//! let conn = listener.connection(Duration::from_secs(5)).unwrap();
+//! assert_eq!(conn.received(), b"hello");
+//! ```
+
+use std::{
+    borrow::Cow,
+    fs::{self, OpenOptions},
+    io::{self, Read, Write},
+    net::{TcpListener, ToSocketAddrs},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use super::{
+    auth::{hex, AuthServer, Authenticated, Mechanism},
+    keyring::random_bytes,
+    percent::path_value,
+    transport::{NonceTcp, Tcp, TcpFamily, Transport, Unix, UnixAddrKind},
+    DBusAddr, Environment, Error, Guid, OwnedDBusAddr, ProcessEnvironment, Result,
+};
+
+const NONCE_LEN: usize = 16;
+const ACCEPT_POLL: Duration = Duration::from_millis(10);
+
+/// A connection accepted by a [`MockListener`], once the client closed it.
+#[derive(Debug)]
+pub struct MockConnection {
+    received: Vec<u8>,
+    handshake_len: usize,
+    auth: Option<Result<Authenticated>>,
+}
+
+impl MockConnection {
+    /// All the bytes received, including the nonce and the authentication handshake.
+    pub fn received(&self) -> &[u8] {
+        &self.received
+    }
+
+    /// The bytes received after the nonce and the authentication handshake.
+    pub fn messages(&self) -> &[u8] {
+        &self.received[self.handshake_len..]
+    }
+
+    /// The outcome of the handshake, if the listener authenticates clients.
+    ///
+    /// This is also an error if the client sent a wrong nonce.
+    pub fn auth(&self) -> Option<&Result<Authenticated>> {
+        self.auth.as_ref()
+    }
+}
+
+/// A [`MockListener`] builder.
+#[derive(Debug, Clone, Default)]
+pub struct MockListenerBuilder {
+    auth: Option<AuthServer>,
+}
+
+impl MockListenerBuilder {
+    /// Authenticate clients with `EXTERNAL` or `ANONYMOUS`, as a server with `guid`.
+    ///
+    /// Clients are assumed to run as the current user, and the client address has the GUID.
+    pub fn guid(self, guid: Guid) -> Self {
+        self.auth(
+            AuthServer::new(guid)
+                .mechanisms([Mechanism::External, Mechanism::Anonymous])
+                .peer_uid(Some(ProcessEnvironment.euid()))
+                .unix_fd(cfg!(unix)),
+        )
+    }
+
+    /// Authenticate clients with the given server.
+    pub fn auth(mut self, auth: AuthServer) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Listen on `addr`.
+    ///
+    /// `unix:` `path`, `dir`, `tmpdir` and `abstract` addresses, as well as `tcp:` and
+    /// `nonce-tcp:` addresses are supported. A zero or missing port picks a free port, and a
+    /// missing `noncefile` is created in the temporary directory. An existing `noncefile` or socket
+    /// path is an error: only what the listener creates is removed on drop.
+    pub fn bind(self, addr: &DBusAddr<'_>) -> Result<MockListener> {
+        let mut cleanup = Cleanup(vec![]);
+        let (listener, transport, nonce) = listen(&addr.transport()?, &mut cleanup)?;
+        let guid = self.auth.as_ref().map(|auth| auth.guid().clone());
+
+        let (tx, connections) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let auth = self.auth;
+        thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok(stream) => {
+                        let (tx, nonce, auth) = (tx.clone(), nonce.clone(), auth.clone());
+                        thread::spawn(move || {
+                            let _ = tx.send(serve(stream, nonce, auth));
+                        });
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(MockListener {
+            addr: OwnedDBusAddr::from_parts(transport, guid),
+            connections,
+            stop,
+            _cleanup: cleanup,
+        })
+    }
+}
+
+/// A listener recording what clients send.
+///
+/// Connections are accepted in the background, and handed over with [`MockListener::connection`]
+/// once closed by the client. Files created by the listener are removed on drop.
+#[derive(Debug)]
+pub struct MockListener {
+    addr: OwnedDBusAddr,
+    connections: mpsc::Receiver<MockConnection>,
+    stop: Arc<AtomicBool>,
+    _cleanup: Cleanup,
+}
+
+impl MockListener {
+    /// A listener builder.
+    pub fn builder() -> MockListenerBuilder {
+        MockListenerBuilder::default()
+    }
+
+    /// Listen on `addr`, without authenticating clients.
+    pub fn bind(addr: &DBusAddr<'_>) -> Result<Self> {
+        Self::builder().bind(addr)
+    }
+
+    /// The address clients should connect to.
+    pub fn addr(&self) -> &OwnedDBusAddr {
+        &self.addr
+    }
+
+    /// Wait for the next connection closed by its client.
+    pub fn connection(&self, timeout: Duration) -> Result<MockConnection> {
+        self.connections
+            .recv_timeout(timeout)
+            .map_err(|_| Error::Other("no connection received".into()))
+    }
+}
+
+impl Drop for MockListener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// Paths created by the listener, removed on drop.
+#[derive(Debug)]
+struct Cleanup(Vec<PathBuf>);
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixListener),
+}
+
+impl Listener {
+    fn set_nonblocking(&self) -> io::Result<()> {
+        match self {
+            Self::Tcp(l) => l.set_nonblocking(true),
+            #[cfg(unix)]
+            Self::Unix(l) => l.set_nonblocking(true),
+        }
+    }
+
+    fn accept(&self) -> io::Result<Box<dyn Stream>> {
+        match self {
+            Self::Tcp(l) => {
+                let (stream, _) = l.accept()?;
+                stream.set_nonblocking(false)?;
+                Ok(Box::new(stream))
+            }
+            #[cfg(unix)]
+            Self::Unix(l) => {
+                let (stream, _) = l.accept()?;
+                stream.set_nonblocking(false)?;
+                Ok(Box::new(stream))
+            }
+        }
+    }
+}
+
+// The listener, the client transport and the expected nonce.
+fn listen(
+    transport: &Transport<'_>,
+    cleanup: &mut Cleanup,
+) -> Result<(Listener, Transport<'static>, Option<Vec<u8>>)> {
+    let (listener, transport, nonce) = match transport {
+        Transport::Unix(unix) => {
+            let (listener, kind) = listen_unix(unix.kind(), cleanup)?;
+            (listener, Transport::Unix(Unix::new(kind)), None)
+        }
+        Transport::Tcp(tcp) => {
+            let (listener, host, port, family) =
+                listen_tcp(tcp.host(), tcp.bind(), tcp.port(), tcp.family())?;
+            let tcp = Tcp::new(Some(host.into()), None, Some(port), Some(family));
+            (Listener::Tcp(listener), Transport::Tcp(tcp), None)
+        }
+        Transport::NonceTcp(nonce_tcp) => {
+            let (listener, host, port, family) = listen_tcp(
+                nonce_tcp.host(),
+                nonce_tcp.bind(),
+                nonce_tcp.port(),
+                nonce_tcp.family(),
+            )?;
+            let noncefile = match nonce_tcp.noncefile() {
+                Some(noncefile) => PathBuf::from(noncefile),
+                None => std::env::temp_dir().join(format!("dbus-nonce-{}", hex(&random_bytes(8)?))),
+            };
+            let nonce = random_bytes(NONCE_LEN)?;
+            write_nonce(&noncefile, &nonce)?;
+            cleanup.0.push(noncefile.clone());
+            let nonce_tcp = NonceTcp::new(
+                Some(host.into()),
+                None,
+                Some(port),
+                Some(family),
+                Some(path_value(noncefile)?),
+            );
+            (
+                Listener::Tcp(listener),
+                Transport::NonceTcp(nonce_tcp),
+                Some(nonce),
+            )
+        }
+        t => return Err(Error::Other(format!("`{t}:` addresses can't be mocked"))),
+    };
+    listener.set_nonblocking()?;
+
+    Ok((listener, transport, nonce))
+}
+
+#[cfg(unix)]
+fn listen_unix(
+    kind: &UnixAddrKind<'_>,
+    cleanup: &mut Cleanup,
+) -> Result<(Listener, UnixAddrKind<'static>)> {
+    use std::os::unix::net::UnixListener;
+
+    let path = match kind {
+        UnixAddrKind::Path(path) => PathBuf::from(&**path),
+        UnixAddrKind::Dir(dir) | UnixAddrKind::Tmpdir(dir) => {
+            Path::new(&**dir).join(format!("dbus-{}", hex(&random_bytes(8)?)))
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        UnixAddrKind::Abstract(name) => {
+            #[cfg(target_os = "android")]
+            use std::os::android::net::SocketAddrExt;
+            #[cfg(target_os = "linux")]
+            use std::os::linux::net::SocketAddrExt;
+            use std::os::unix::net::SocketAddr;
+
+            let listener = UnixListener::bind_addr(&SocketAddr::from_abstract_name(name)?)?;
+            return Ok((
+                Listener::Unix(listener),
+                UnixAddrKind::Abstract(Cow::Owned(name.to_vec())),
+            ));
+        }
+        _ => {
+            return Err(Error::Other(
+                "only `path`, `dir`, `tmpdir` and `abstract` `unix:` addresses can be mocked"
+                    .into(),
+            ))
+        }
+    };
+    let listener = UnixListener::bind(&path)?;
+    cleanup.0.push(path.clone());
+
+    Ok((
+        Listener::Unix(listener),
+        UnixAddrKind::Path(path_value(path)?),
+    ))
+}
+
+#[cfg(not(unix))]
+fn listen_unix(
+    _kind: &UnixAddrKind<'_>,
+    _cleanup: &mut Cleanup,
+) -> Result<(Listener, UnixAddrKind<'static>)> {
+    Err(Error::Other(
+        "`unix:` addresses can't be mocked on this platform".into(),
+    ))
+}
+
+// The listener, and the address to connect to.
+fn listen_tcp(
+    host: Option<&str>,
+    bind: Option<&str>,
+    port: Option<u16>,
+    family: Option<TcpFamily>,
+) -> Result<(TcpListener, String, u16, TcpFamily)> {
+    let host = bind.filter(|b| *b != "*").or(host).unwrap_or("localhost");
+
+    let mut last_err = None;
+    for addr in (host, port.unwrap_or(0)).to_socket_addrs()? {
+        match family {
+            Some(TcpFamily::IPv4) if !addr.is_ipv4() => continue,
+            Some(TcpFamily::IPv6) if !addr.is_ipv6() => continue,
+            _ => {}
+        }
+        match TcpListener::bind(addr) {
+            Ok(listener) => {
+                let local = listener.local_addr()?;
+                let family = if local.is_ipv4() {
+                    TcpFamily::IPv4
+                } else {
+                    TcpFamily::IPv6
+                };
+                return Ok((listener, local.ip().to_string(), local.port(), family));
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    match last_err {
+        Some(e) => Err(e.into()),
+        None => Err(Error::Other(format!("`{host}` has no matching address"))),
+    }
+}
+
+fn write_nonce(path: &Path, nonce: &[u8]) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(path)?.write_all(nonce)?;
+
+    Ok(())
+}
+
+// Records what is read from the stream.
+struct Recorder {
+    stream: Box<dyn Stream>,
+    received: Vec<u8>,
+}
+
+impl Read for Recorder {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stream.read(buf)?;
+        self.received.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl Write for Recorder {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+fn serve(
+    stream: Box<dyn Stream>,
+    nonce: Option<Vec<u8>>,
+    auth: Option<AuthServer>,
+) -> MockConnection {
+    let mut stream = Recorder {
+        stream,
+        received: vec![],
+    };
+
+    let mut res = None;
+    if let Some(nonce) = nonce {
+        let mut received = vec![0; nonce.len()];
+        if stream.read_exact(&mut received).is_err() || received != nonce {
+            res = Some(Err(Error::Other("wrong nonce".into())));
+        }
+    }
+    if res.is_none() {
+        res = auth.map(|auth| auth.authenticate(&mut stream));
+    }
+    let handshake_len = stream.received.len();
+    if !matches!(res, Some(Err(_))) {
+        let _ = io::copy(&mut stream, &mut io::sink());
+    }
+
+    MockConnection {
+        received: stream.received,
+        handshake_len,
+        auth: res,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpStream;
+
+    use super::*;
+    use crate::{auth::AuthClient, ProbeOptions};
+
+    const GUID: &str = "9406e28972c595c590766c9564ce623f";
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[cfg(unix)]
+    #[test]
+    fn unix() {
+        use std::os::unix::net::UnixStream;
+
+        let tmp = crate::tests::TmpDir::new("mock-unix");
+        let addr = format!("unix:tmpdir={}", tmp.path().display());
+        let listener = MockListener::builder()
+            .guid(Guid::try_from(GUID).unwrap())
+            .bind(&DBusAddr::try_from(addr.as_str()).unwrap())
+            .unwrap();
+        let addr = listener.addr().clone();
+        assert_eq!(addr.guid().unwrap().to_string(), GUID);
+        let Transport::Unix(unix) = addr.transport() else {
+            panic!()
+        };
+        let UnixAddrKind::Path(path) = unix.kind() else {
+            panic!()
+        };
+
+        let mut stream = UnixStream::connect(&**path).unwrap();
+        let auth = AuthClient::new()
+            .guid(addr.guid().cloned())
+            .negotiate_unix_fd(true)
+            .authenticate(&mut stream)
+            .unwrap();
+        assert!(auth.unix_fd());
+        stream.write_all(b"hello").unwrap();
+        drop(stream);
+
+        let conn = listener.connection(TIMEOUT).unwrap();
+        assert_eq!(
+            conn.auth().unwrap().as_ref().unwrap().mechanism(),
+            Mechanism::External
+        );
+        assert!(conn.received().starts_with(b"\0AUTH EXTERNAL "));
+        assert_eq!(conn.messages(), b"hello");
+
+        let path = PathBuf::from(&**path);
+        assert!(path.exists());
+        drop(listener);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn tcp() {
+        let listener =
+            MockListener::bind(&DBusAddr::try_from("tcp:host=127.0.0.1,port=0").unwrap()).unwrap();
+        let Transport::Tcp(tcp) = listener.addr().transport() else {
+            panic!()
+        };
+        assert_eq!(tcp.host(), Some("127.0.0.1"));
+        assert_ne!(tcp.port(), Some(0));
+        assert_eq!(tcp.family(), Some(TcpFamily::IPv4));
+
+        let mut stream = TcpStream::connect((tcp.host().unwrap(), tcp.port().unwrap())).unwrap();
+        stream.write_all(b"\0AUTH\r\n").unwrap();
+        drop(stream);
+        let conn = listener.connection(TIMEOUT).unwrap();
+        assert!(conn.auth().is_none());
+        assert_eq!(conn.messages(), b"\0AUTH\r\n");
+
+        assert!(listener.connection(Duration::from_millis(10)).is_err());
+    }
+
+    #[test]
+    fn nonce_tcp() {
+        let listener = MockListener::builder()
+            .guid(Guid::try_from(GUID).unwrap())
+            .bind(&DBusAddr::try_from("nonce-tcp:host=127.0.0.1").unwrap())
+            .unwrap();
+        let addr = DBusAddr::try_from(listener.addr().to_string()).unwrap();
+        let report = addr.probe_with(&ProbeOptions::new().timeout(TIMEOUT));
+        assert!(report.is_ok(), "{:?}", report.error());
+        assert_eq!(
+            report.mechanisms().unwrap(),
+            ["EXTERNAL".to_string(), "ANONYMOUS".to_string()]
+        );
+
+        let conn = listener.connection(TIMEOUT).unwrap();
+        assert_eq!(conn.received().len(), NONCE_LEN + b"\0AUTH\r\n".len());
+        assert!(conn.received().ends_with(b"\0AUTH\r\n"));
+        assert!(conn.auth().unwrap().is_err());
+
+        // without the nonce
+        let Transport::NonceTcp(nonce_tcp) = listener.addr().transport() else {
+            panic!()
+        };
+        let noncefile = PathBuf::from(nonce_tcp.noncefile().unwrap());
+        let mut stream =
+            TcpStream::connect((nonce_tcp.host().unwrap(), nonce_tcp.port().unwrap())).unwrap();
+        stream.write_all(&[0; NONCE_LEN]).unwrap();
+        drop(stream);
+        let conn = listener.connection(TIMEOUT).unwrap();
+        assert_eq!(
+            conn.auth().unwrap().as_ref().unwrap_err(),
+            &Error::Other("wrong nonce".into())
+        );
+
+        assert!(noncefile.exists());
+        drop(listener);
+        assert!(!noncefile.exists());
+    }
+
+    #[test]
+    fn existing_noncefile() {
+        let tmp = crate::tests::TmpDir::new("mock-nonce");
+        let noncefile = tmp.path().join("nonce");
+        fs::write(&noncefile, "keep").unwrap();
+        let addr = format!(
+            "nonce-tcp:host=127.0.0.1,port=0,noncefile={}",
+            noncefile.display()
+        );
+        assert!(MockListener::bind(&DBusAddr::try_from(addr.as_str()).unwrap()).is_err());
+        assert_eq!(fs::read_to_string(&noncefile).unwrap(), "keep");
+    }
+}
@@ -1,12 +1,49 @@
-use std::{borrow::Cow, ffi::OsStr};
+use std::borrow::Cow;
+#[cfg(feature = "std")]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use proptest::prelude::*;
 
-#[cfg(target_os = "windows")]
-use super::transport::AutolaunchScope;
+#[cfg(feature = "std")]
+use super::{transport::Unixexec, BusType};
 use super::{
-    transport::{TcpFamily, Transport, UnixAddrKind},
-    DBusAddr,
+    transport::{AutolaunchScope, MachineRef, TcpFamily, Transport, UnixAddrKind},
+    DBusAddr, DBusAddrList, Error, Guid, OwnedDBusAddr, ToOwnedDBusAddrs,
 };
 
+// A temporary directory, removed on drop.
+#[cfg(feature = "std")]
+pub(crate) struct TmpDir(PathBuf);
+
+#[cfg(feature = "std")]
+impl TmpDir {
+    pub(crate) fn new(name: &str) -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path =
+            std::env::temp_dir().join(format!("dbus-addr-{name}-{}-{n}", std::process::id()));
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for TmpDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
 #[test]
 fn parse_err() {
     assert_eq!(
@@ -46,6 +83,26 @@ fn parse_err() {
     addr.guid().unwrap();
 }
 
+#[test]
+fn parse_guid() {
+    let guid = Guid::try_from("9406e28972c595c590766c9564ce623F").unwrap();
+    assert_eq!(guid.to_string(), "9406e28972c595c590766c9564ce623f");
+
+    // `from_str_radix()` would accept a sign, and split a multi-byte char
+    for s in [
+        "+406e28972c595c590766c9564ce623f",
+        "-406e28972c595c590766c9564ce623f",
+        "0\u{e9}6e28972c595c590766c9564ce623f",
+        "\u{e9}06e28972c595c590766c9564ce623f",
+    ] {
+        assert_eq!(s.len(), 32);
+        assert_eq!(
+            Guid::try_from(s).unwrap_err(),
+            Error::InvalidValue("guid".into())
+        );
+    }
+}
+
 #[test]
 fn parse_unix() {
     let addr = DBusAddr::try_from("unix:path=/tmp/dbus-foo,guid=9406e28972c595c590766c9564ce623f")
@@ -53,10 +110,10 @@ fn parse_unix() {
     let Transport::Unix(u) = addr.transport().unwrap() else {
         panic!();
     };
-    assert_eq!(
-        u.kind(),
-        &UnixAddrKind::Path(Cow::Borrowed(OsStr::new("/tmp/dbus-foo")))
-    );
+    let UnixAddrKind::Path(path) = u.kind() else {
+        panic!();
+    };
+    assert_eq!(&**path, "/tmp/dbus-foo");
 
     assert_eq!(
         DBusAddr::try_from("unix:foo=blah").unwrap_err().to_string(),
@@ -77,7 +134,6 @@ fn parse_unix() {
     DBusAddr::try_from(String::from("unix:path=/tmp/foo")).unwrap();
 }
 
-#[cfg(target_os = "macos")]
 #[test]
 fn parse_launchd() {
     let addr = DBusAddr::try_from("launchd:env=FOOBAR").unwrap();
@@ -92,7 +148,6 @@ fn parse_launchd() {
     );
 }
 
-#[cfg(target_os = "linux")]
 #[test]
 fn parse_systemd() {
     let addr = DBusAddr::try_from("systemd:").unwrap();
@@ -153,14 +208,150 @@ fn parse_unixexec() {
     );
 }
 
+#[cfg(feature = "std")]
+#[test]
+fn unixexec_ssh() {
+    for (host, user, bus, addr) in [
+        (
+            "example.com",
+            None,
+            BusType::System,
+            "unixexec:path=ssh,argv1=-xT,argv2=--,argv3=example.com,argv4=systemd-stdio-bridge",
+        ),
+        (
+            "example.com",
+            Some("alice"),
+            BusType::Session,
+            "unixexec:path=ssh,argv1=-xT,argv2=--,argv3=alice%40example.com,\
+             argv4=systemd-stdio-bridge,argv5=--user",
+        ),
+        (
+            "::1",
+            None,
+            BusType::from_str("unix:path=/run/user/1000/bus").unwrap(),
+            "unixexec:path=ssh,argv1=-xT,argv2=--,argv3=%3A%3A1,argv4=systemd-stdio-bridge,\
+             argv5=--bus-path%3Dunix%3Apath%3D/run/user/1000/bus",
+        ),
+    ] {
+        let ssh = Unixexec::ssh(host, user, &bus).unwrap();
+        let owned = OwnedDBusAddr::from(Transport::Unixexec(ssh.clone()));
+        assert_eq!(owned.to_string(), addr);
+
+        let tunnel = ssh.ssh_tunnel().unwrap();
+        assert_eq!(tunnel.host(), host);
+        assert_eq!(tunnel.user(), user);
+        assert_eq!(tunnel.remote_bus(), &bus);
+    }
+
+    // the remote shell must not split nor expand the address
+    let bus = BusType::from_str("unix:path=/tmp/bus;x-$(id):my key=1").unwrap();
+    let ssh = Unixexec::ssh("host", None, &bus).unwrap();
+    assert_eq!(
+        ssh.argv().last().unwrap().1,
+        "--bus-path='unix:path=/tmp/bus;x-$(id):my key=1'"
+    );
+    assert_eq!(ssh.ssh_tunnel().unwrap().remote_bus(), &bus);
+    let bus = BusType::Address(DBusAddrList::try_from("unix:path=/tmp/bus;foo").unwrap());
+    assert!(Unixexec::ssh("host", None, &bus).is_err());
+
+    assert!(Unixexec::ssh("", None, &BusType::System).is_err());
+    assert!(Unixexec::ssh("a@b", None, &BusType::System).is_err());
+    assert!(Unixexec::ssh("host", None, &BusType::Starter).is_err());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn unixexec_ssh_tunnel() {
+    let tunnel = |addr: &str| {
+        let addr = DBusAddr::try_from(addr).unwrap();
+        let Transport::Unixexec(t) = addr.transport().unwrap() else {
+            panic!();
+        };
+        t.ssh_tunnel().map(|t| {
+            (
+                t.host().to_owned(),
+                t.user().map(str::to_owned),
+                t.remote_bus().clone(),
+            )
+        })
+    };
+
+    assert_eq!(
+        tunnel(
+            "unixexec:path=/usr/bin/ssh,argv0=ssh,argv1=-p,argv2=2222,argv3=-lbob,argv4=host,\
+             argv5=/usr/bin/systemd-stdio-bridge,argv6=--bus-path,argv7=unix%3apath%3d/tmp/bus"
+        ),
+        Some((
+            "host".into(),
+            Some("bob".into()),
+            BusType::from_str("unix:path=/tmp/bus").unwrap()
+        ))
+    );
+    assert_eq!(
+        tunnel("unixexec:path=ssh,argv1=host,argv2=systemd-stdio-bridge,argv3=--system"),
+        Some(("host".into(), None, BusType::System))
+    );
+    for addr in [
+        "unixexec:path=/bin/sh,argv1=host,argv2=systemd-stdio-bridge",
+        "unixexec:path=ssh,argv1=host,argv2=dbus-stdio",
+        "unixexec:path=ssh,argv1=host,argv3=systemd-stdio-bridge",
+        "unixexec:path=ssh,argv1=-p,argv2=22",
+        "unixexec:path=ssh,argv1=host,argv2=systemd-stdio-bridge,argv3=-M,argv4=foo",
+        "unixexec:path=ssh,argv1=host,argv2=systemd-stdio-bridge,argv3=--bus-path%3dfoo",
+        // unquoted, the remote shell would run `unix:path=/b` as a command
+        "unixexec:path=ssh,argv1=host,argv2=systemd-stdio-bridge,\
+         argv3=--bus-path%3dunix%3apath%3d/a%3bunix%3apath%3d/b",
+    ] {
+        assert_eq!(tunnel(addr), None, "{addr}");
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn unixexec_command_line() {
+    let exec = Unixexec::from_command_line(
+        r#"/usr/bin/ssh -xT -o "ProxyCommand=nc %h 22" 'host' systemd-stdio-bridge"#,
+    )
+    .unwrap();
+    assert_eq!(exec.path(), "/usr/bin/ssh");
+    assert_eq!(exec.argv()[2], (3, Cow::from("ProxyCommand=nc %h 22")));
+
+    let owned = OwnedDBusAddr::from(Transport::Unixexec(exec.clone()));
+    assert_eq!(
+        owned.to_string(),
+        "unixexec:path=/usr/bin/ssh,argv1=-xT,argv2=-o,argv3=ProxyCommand%3Dnc%20%25h%2022,\
+         argv4=host,argv5=systemd-stdio-bridge"
+    );
+    let s = owned.to_string();
+    let parsed = DBusAddr::try_from(s.as_str()).unwrap();
+    let Transport::Unixexec(parsed) = parsed.transport().unwrap() else {
+        panic!();
+    };
+    assert_eq!(parsed, exec);
+    assert_eq!(
+        parsed.to_command_line(),
+        "/usr/bin/ssh -xT -o 'ProxyCommand=nc %h 22' host systemd-stdio-bridge"
+    );
+
+    // `argv0` is left out
+    let addr =
+        DBusAddr::try_from("unixexec:path=/bin/sh,argv0=sh,argv1=-c,argv2=echo%20%24x").unwrap();
+    let Transport::Unixexec(exec) = addr.transport().unwrap() else {
+        panic!();
+    };
+    assert_eq!(exec.to_command_line(), "/bin/sh -c 'echo $x'");
+
+    assert!(Unixexec::from_command_line("").is_err());
+    assert!(Unixexec::from_command_line("'' foo").is_err());
+    assert!(Unixexec::from_command_line("a; b").is_err());
+}
+
 #[test]
 fn parse_autolaunch() {
     let addr = DBusAddr::try_from("autolaunch:scope=*user").unwrap();
-    #[allow(unused)]
     let Transport::Autolaunch(t) = addr.transport().unwrap() else {
         panic!();
     };
-    #[cfg(target_os = "windows")]
     assert_eq!(t.scope().unwrap(), &AutolaunchScope::User);
 }
 
@@ -182,6 +373,95 @@ fn parse_vsock() {
     );
 }
 
+#[test]
+fn parse_machine() {
+    let addr = DBusAddr::try_from("x-machine-unix:machine=foo").unwrap();
+    let Transport::MachineUnix(t) = addr.transport().unwrap() else {
+        panic!();
+    };
+    assert_eq!(t.machine(), &MachineRef::Name("foo".into()));
+    assert_eq!(t.path(), None);
+    assert_eq!(t.socket_path(), "/run/dbus/system_bus_socket");
+
+    let addr = DBusAddr::try_from("x-machine-exec:pid=42,path=/bin/test,argv1=foo").unwrap();
+    let Transport::MachineExec(t) = addr.transport().unwrap() else {
+        panic!();
+    };
+    assert_eq!(t.machine(), &MachineRef::Pid(42));
+    assert_eq!(t.path(), "/bin/test");
+    assert_eq!(t.argv(), &[(1, Cow::from("foo"))]);
+
+    assert_eq!(
+        DBusAddr::try_from("x-machine-unix:machine=foo,pid=1")
+            .unwrap_err()
+            .to_string(),
+        "Other error: invalid `x-machine-unix:` address, only one of `machine` or `pid` expected"
+    );
+    assert_eq!(
+        DBusAddr::try_from("x-machine-exec:machine=foo")
+            .unwrap_err()
+            .to_string(),
+        "Missing key: `path`"
+    );
+}
+
+#[test]
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+fn resolve_machine() {
+    use std::os::unix::net::UnixListener;
+
+    let dir = TmpDir::new("machine");
+    let path = dir.path().join("bus");
+    let _listener = UnixListener::bind(&path).unwrap();
+
+    let addr = format!(
+        "x-machine-unix:pid={},path={}",
+        std::process::id(),
+        path.display()
+    );
+    let addr = OwnedDBusAddr::try_from(addr.as_str()).unwrap();
+    let Transport::MachineUnix(t) = addr.transport() else {
+        panic!();
+    };
+    let root = PathBuf::from(format!("/proc/{}/root", std::process::id()));
+    let resolved = root.join(path.strip_prefix("/").unwrap());
+    assert_eq!(t.resolve().unwrap(), resolved);
+
+    let report = DBusAddr::try_from(addr.to_string().as_str())
+        .unwrap()
+        .probe_with(&crate::ProbeOptions::new().auth(false));
+    assert_eq!(report.error(), None);
+    assert_eq!(report.endpoint(), Some(&crate::Endpoint::Unix(resolved)));
+
+    let addr = OwnedDBusAddr::try_from("x-machine-unix:machine=foo").unwrap();
+    let Transport::MachineUnix(t) = addr.transport() else {
+        panic!();
+    };
+    assert!(t.resolve().is_err());
+}
+
+#[test]
+fn fmt_all_transports() {
+    for addr in [
+        "unix:guid=9406e28972c595c590766c9564ce623f,path=/tmp/dbus-foo",
+        "launchd:env=DBUS_LAUNCHD_SESSION_BUS_SOCKET",
+        "systemd:",
+        "tcp:host=localhost,port=1234,family=ipv4",
+        "nonce-tcp:host=localhost,port=1234,noncefile=/tmp/nonce",
+        "unixexec:path=/bin/test,argv1=foo",
+        "autolaunch:scope=*install-path",
+        "autolaunch:scope=release",
+        "vsock:cid=12,port=32",
+        "x-machine-unix:machine=foo",
+        "x-machine-unix:pid=42,path=/run/bus",
+        "x-machine-exec:machine=.host,path=/bin/test,argv1=foo",
+    ] {
+        let owned = OwnedDBusAddr::try_from(addr).unwrap();
+        assert!(!matches!(owned.transport(), Transport::Other(_)));
+        assert_eq!(owned.to_string(), addr);
+    }
+}
+
 #[test]
 fn parse_other() {
     const ADDR: &str = "foo:arg=val";
@@ -191,3 +471,105 @@ fn parse_other() {
     assert!(matches!(transport, Transport::Other(Cow::Borrowed("foo"))));
     assert_eq!(addr.as_str(), ADDR);
 }
+
+#[test]
+fn listen_only() {
+    for (addr, listen_only) in [
+        ("unix:path=/tmp/bus", false),
+        ("unix:abstract=/tmp/bus", false),
+        ("unix:tmpdir=/tmp", true),
+        ("unix:runtime=yes", true),
+        ("systemd:", true),
+        ("tcp:host=localhost,port=1234", false),
+        ("tcp:host=localhost,port=0", true),
+        ("nonce-tcp:port=1234", true),
+        ("nonce-tcp:port=1234,noncefile=/tmp/nonce", false),
+        ("unixexec:path=/bin/true", false),
+        ("x-machine-unix:machine=foo", false),
+        ("foo:", false),
+    ] {
+        let addr = DBusAddr::try_from(addr).unwrap();
+        assert_eq!(
+            addr.transport().unwrap().is_listen_only(),
+            listen_only,
+            "{}",
+            addr.as_str()
+        );
+    }
+}
+
+proptest! {
+    #[test]
+    fn round_trip(addr in any::<OwnedDBusAddr>()) {
+        let s = addr.to_string();
+        let parsed = OwnedDBusAddr::try_from(s.as_str()).unwrap();
+        prop_assert_eq!(&parsed, &addr);
+        prop_assert_eq!(parsed.to_string(), s.as_str());
+
+        let borrowed = DBusAddr::try_from(s.as_str()).unwrap();
+        prop_assert_eq!(&borrowed.transport().unwrap(), addr.transport());
+        prop_assert_eq!(borrowed.guid().unwrap(), addr.guid().cloned());
+    }
+
+    #[test]
+    fn round_trip_list(list in any::<DBusAddrList<'static>>()) {
+        let s = list.to_string();
+        let parsed = DBusAddrList::try_from(s.as_str()).unwrap();
+        let addrs = parsed
+            .to_owned_dbus_addrs()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        prop_assert!(!addrs.is_empty());
+        prop_assert_eq!(DBusAddrList::from_addrs(&addrs), list);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn round_trip_command_line(path in ".+", args in prop::collection::vec(any::<String>(), 0..8)) {
+        let line = crate::shell::join_command_line(
+            std::iter::once(path.as_str()).chain(args.iter().map(String::as_str)),
+        );
+        let exec = Unixexec::from_command_line(&line).unwrap();
+        prop_assert_eq!(exec.path(), path.as_str());
+        prop_assert_eq!(exec.argv().len(), args.len());
+
+        let owned = OwnedDBusAddr::from(Transport::Unixexec(exec.clone()));
+        let parsed = OwnedDBusAddr::try_from(owned.to_string()).unwrap();
+        let Transport::Unixexec(parsed) = parsed.transport() else {
+            panic!();
+        };
+        prop_assert_eq!(parsed, &exec);
+        prop_assert_eq!(parsed.to_command_line(), line);
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn arbitrary_round_trip() {
+    use arbitrary::Unstructured;
+
+    // a xorshift byte stream, for reproducible inputs
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let data: Vec<u8> = (0..1 << 16)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+
+    let mut u = Unstructured::new(&data);
+    while !u.is_empty() {
+        let list: DBusAddrList<'_> = u.arbitrary().unwrap();
+        let s = list.to_string();
+        for addr in DBusAddrList::try_from(s.as_str())
+            .unwrap()
+            .to_owned_dbus_addrs()
+        {
+            let addr = addr.unwrap();
+            let parsed = OwnedDBusAddr::try_from(addr.to_string()).unwrap();
+            assert_eq!(parsed, addr);
+        }
+    }
+}
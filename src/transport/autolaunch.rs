@@ -1,46 +1,40 @@
-use std::marker::PhantomData;
-#[cfg(target_os = "windows")]
-use std::{borrow::Cow, fmt};
+use alloc::borrow::Cow;
+use core::fmt;
 
-#[cfg(target_os = "windows")]
-use super::percent::decode_percents_str;
-use super::{DBusAddr, KeyValFmt, Result, TransportImpl};
+use super::{percent::decode_percents_str, DBusAddr, KeyValFmt, Result, TransportImpl};
 
 /// `autolaunch:` D-Bus transport.
 ///
 /// <https://dbus.freedesktop.org/doc/dbus-specification.html#meta-transports-autolaunch>
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub struct Autolaunch<'a> {
-    #[cfg(target_os = "windows")]
     scope: Option<AutolaunchScope<'a>>,
-    phantom: PhantomData<&'a ()>,
 }
 
 impl<'a> Autolaunch<'a> {
-    #[cfg(target_os = "windows")]
-    /// Scope of autolaunch (Windows only)
+    pub(crate) fn new(scope: Option<AutolaunchScope<'a>>) -> Self {
+        Self { scope }
+    }
+
+    /// Scope of autolaunch.
+    ///
+    /// The scope is only used by the Windows autolaunch implementation.
     pub fn scope(&self) -> Option<&AutolaunchScope<'a>> {
         self.scope.as_ref()
     }
 
     /// Convert into owned version, with 'static lifetime.
     pub fn into_owned(self) -> Autolaunch<'static> {
-        Autolaunch {
-            #[cfg(target_os = "windows")]
-            scope: self.scope.map(|s| s.into_owned()),
-            phantom: PhantomData,
-        }
+        Autolaunch::new(self.scope.map(|s| s.into_owned()))
     }
 }
 
 impl<'a> TransportImpl<'a> for Autolaunch<'a> {
     fn for_address(s: &'a DBusAddr<'a>) -> Result<Self> {
-        #[allow(unused_mut)]
         let mut res = Autolaunch::default();
 
         for (k, v) in s.key_val_iter() {
             match (k, v) {
-                #[cfg(target_os = "windows")]
                 ("scope", Some(v)) => {
                     res.scope = Some(decode_percents_str(v)?.try_into()?);
                 }
@@ -52,14 +46,11 @@ impl<'a> TransportImpl<'a> for Autolaunch<'a> {
     }
 
     fn fmt_key_val<'s: 'b, 'b>(&'s self, kv: KeyValFmt<'b>) -> KeyValFmt<'b> {
-        #[cfg(target_os = "windows")]
-        let kv = kv.add("scope", self.scope());
-        kv
+        kv.add("scope", self.scope())
     }
 }
 
-/// Scope of autolaunch (Windows only)
-#[cfg(target_os = "windows")]
+/// Scope of autolaunch (used on Windows)
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum AutolaunchScope<'a> {
@@ -71,7 +62,6 @@ pub enum AutolaunchScope<'a> {
     Other(Cow<'a, str>),
 }
 
-#[cfg(target_os = "windows")]
 impl<'a> AutolaunchScope<'a> {
     fn into_owned(self) -> AutolaunchScope<'static> {
         match self {
@@ -82,7 +72,6 @@ impl<'a> AutolaunchScope<'a> {
     }
 }
 
-#[cfg(target_os = "windows")]
 impl fmt::Display for AutolaunchScope<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -93,7 +82,6 @@ impl fmt::Display for AutolaunchScope<'_> {
     }
 }
 
-#[cfg(target_os = "windows")]
 impl<'a> TryFrom<Cow<'a, str>> for AutolaunchScope<'a> {
     type Error = super::Error;
 
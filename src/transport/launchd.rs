@@ -1,4 +1,4 @@
-use std::borrow::Cow;
+use alloc::borrow::Cow;
 
 use super::{percent::decode_percents_str, DBusAddr, Error, KeyValFmt, Result, TransportImpl};
 
@@ -11,6 +11,10 @@ pub struct Launchd<'a> {
 }
 
 impl<'a> Launchd<'a> {
+    pub(crate) fn new(env: Cow<'a, str>) -> Self {
+        Self { env }
+    }
+
     /// Environment variable.
     ///
     /// Environment variable used to get the path of the unix domain socket for the launchd created
@@ -32,9 +36,7 @@ impl<'a> TransportImpl<'a> for Launchd<'a> {
         for (k, v) in s.key_val_iter() {
             match (k, v) {
                 ("env", Some(v)) => {
-                    return Ok(Launchd {
-                        env: decode_percents_str(v)?,
-                    });
+                    return Ok(Launchd::new(decode_percents_str(v)?));
                 }
                 _ => continue,
             }
@@ -0,0 +1,234 @@
+use alloc::{borrow::Cow, vec::Vec};
+
+use super::{
+    percent::decode_percents_str, unixexec::Argv, DBusAddr, Error, KeyValFmt, Result, TransportImpl,
+};
+
+/// The machine an `x-machine-unix:` or `x-machine-exec:` address refers to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MachineRef<'a> {
+    /// A container or VM registered with systemd-machined, or `.host`.
+    Name(Cow<'a, str>),
+    /// The machine a process runs in, by its process ID on the host.
+    Pid(u32),
+}
+
+impl MachineRef<'_> {
+    fn fmt_key_val<'s: 'b, 'b>(&'s self, kv: KeyValFmt<'b>) -> KeyValFmt<'b> {
+        match self {
+            MachineRef::Name(name) => kv.add("machine", Some(name)),
+            MachineRef::Pid(pid) => kv.add("pid", Some(pid)),
+        }
+    }
+
+    fn into_owned(self) -> MachineRef<'static> {
+        match self {
+            MachineRef::Name(name) => MachineRef::Name(name.into_owned().into()),
+            MachineRef::Pid(pid) => MachineRef::Pid(pid),
+        }
+    }
+
+    // The `machine` or `pid` key, only one of which is expected.
+    fn for_address<'a>(s: &'a DBusAddr<'a>, transport: &str) -> Result<MachineRef<'a>> {
+        let mut machine = None;
+        for (k, v) in s.key_val_iter() {
+            let m = match (k, v) {
+                ("machine", Some(v)) => MachineRef::Name(decode_percents_str(v)?),
+                ("pid", Some(v)) => MachineRef::Pid(
+                    decode_percents_str(v)?
+                        .parse()
+                        .map_err(|_| Error::InvalidValue(k.into()))?,
+                ),
+                _ => continue,
+            };
+            if machine.replace(m).is_some() {
+                return Err(Error::Other(alloc::format!(
+                    "invalid `{transport}:` address, only one of `machine` or `pid` expected"
+                )));
+            }
+        }
+
+        machine.ok_or_else(|| {
+            Error::Other(alloc::format!(
+                "invalid `{transport}:` address, missing `machine` or `pid`"
+            ))
+        })
+    }
+}
+
+/// `x-machine-unix:` sd-bus transport.
+///
+/// The Unix socket at `path` inside a local container, by default the system bus socket.
+///
+/// <https://www.freedesktop.org/software/systemd/man/latest/sd_bus_set_address.html>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MachineUnix<'a> {
+    machine: MachineRef<'a>,
+    path: Option<Cow<'a, str>>,
+}
+
+impl<'a> MachineUnix<'a> {
+    /// The system bus socket, when no `path` is given.
+    pub const DEFAULT_PATH: &'static str = "/run/dbus/system_bus_socket";
+
+    pub(crate) fn new(machine: MachineRef<'a>, path: Option<Cow<'a, str>>) -> Self {
+        Self { machine, path }
+    }
+
+    /// The machine, from the `machine` or `pid` key.
+    pub fn machine(&self) -> &MachineRef<'a> {
+        &self.machine
+    }
+
+    /// The socket path inside the machine, if given.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    /// The socket path inside the machine, or [`Self::DEFAULT_PATH`].
+    pub fn socket_path(&self) -> &str {
+        self.path().unwrap_or(Self::DEFAULT_PATH)
+    }
+
+    /// The socket path as seen from the host, resolved under the machine root directory.
+    ///
+    /// Only `pid` addresses, whose root directory is `/proc/<pid>/root`, and the `.host` machine
+    /// can be resolved: other machine names would require asking systemd-machined.
+    #[cfg(all(feature = "std", any(target_os = "linux", target_os = "android")))]
+    pub fn resolve(&self) -> Result<std::path::PathBuf> {
+        let root = match &self.machine {
+            MachineRef::Pid(pid) => crate::RootDir::for_pid(*pid),
+            MachineRef::Name(name) if name == ".host" => crate::RootDir::new("/"),
+            MachineRef::Name(name) => {
+                return Err(Error::Other(alloc::format!(
+                    "unable to resolve machine `{name}` without systemd-machined"
+                )))
+            }
+        };
+
+        root.resolve(self.socket_path())
+    }
+
+    /// Convert into owned version, with 'static lifetime.
+    pub fn into_owned(self) -> MachineUnix<'static> {
+        MachineUnix {
+            machine: self.machine.into_owned(),
+            path: self.path.map(|p| p.into_owned().into()),
+        }
+    }
+}
+
+impl<'a> TransportImpl<'a> for MachineUnix<'a> {
+    fn for_address(s: &'a DBusAddr<'a>) -> Result<Self> {
+        let machine = MachineRef::for_address(s, "x-machine-unix")?;
+        let mut path = None;
+        for (k, v) in s.key_val_iter() {
+            if let ("path", Some(v)) = (k, v) {
+                path = Some(decode_percents_str(v)?);
+            }
+        }
+
+        Ok(Self::new(machine, path))
+    }
+
+    fn fmt_key_val<'s: 'b, 'b>(&'s self, kv: KeyValFmt<'b>) -> KeyValFmt<'b> {
+        self.machine.fmt_key_val(kv).add("path", self.path())
+    }
+}
+
+/// `x-machine-exec:` sd-bus transport.
+///
+/// Like `unixexec:`, with the binary executed inside a local container.
+///
+/// <https://www.freedesktop.org/software/systemd/man/latest/sd_bus_set_address.html>
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MachineExec<'a> {
+    machine: MachineRef<'a>,
+    path: Cow<'a, str>,
+    argv: Vec<(usize, Cow<'a, str>)>,
+}
+
+impl<'a> MachineExec<'a> {
+    // `argv` must be sorted by index, as when parsed.
+    pub(crate) fn new(
+        machine: MachineRef<'a>,
+        path: Cow<'a, str>,
+        argv: Vec<(usize, Cow<'a, str>)>,
+    ) -> Self {
+        Self {
+            machine,
+            path,
+            argv,
+        }
+    }
+
+    /// The machine, from the `machine` or `pid` key.
+    pub fn machine(&self) -> &MachineRef<'a> {
+        &self.machine
+    }
+
+    /// Binary to execute inside the machine.
+    pub fn path(&self) -> &str {
+        self.path.as_ref()
+    }
+
+    /// Arguments.
+    ///
+    /// Arguments to pass to the binary as `[(nth, arg),...]`.
+    pub fn argv(&self) -> &[(usize, Cow<'a, str>)] {
+        self.argv.as_ref()
+    }
+
+    /// Convert into owned version, with 'static lifetime.
+    pub fn into_owned(self) -> MachineExec<'static> {
+        let argv = self
+            .argv
+            .into_iter()
+            .map(|(index, cow)| (index, cow.into_owned().into()))
+            .collect();
+        MachineExec {
+            machine: self.machine.into_owned(),
+            path: self.path.into_owned().into(),
+            argv,
+        }
+    }
+}
+
+impl<'a> TransportImpl<'a> for MachineExec<'a> {
+    fn for_address(s: &'a DBusAddr<'a>) -> Result<Self> {
+        let machine = MachineRef::for_address(s, "x-machine-exec")?;
+        let mut path = None;
+        let mut argv = Vec::new();
+
+        for (k, v) in s.key_val_iter() {
+            match (k, v) {
+                ("path", Some(v)) => {
+                    path = Some(decode_percents_str(v)?);
+                }
+                (k, Some(v)) if k.starts_with("argv") => {
+                    let n: usize = k[4..].parse().map_err(|_| Error::InvalidValue(k.into()))?;
+                    argv.push((n, decode_percents_str(v)?));
+                }
+                _ => continue,
+            }
+        }
+
+        let Some(path) = path else {
+            return Err(Error::MissingKey("path".into()));
+        };
+
+        argv.sort_by_key(|(num, _)| *num);
+
+        Ok(Self::new(machine, path, argv))
+    }
+
+    fn fmt_key_val<'s: 'b, 'b>(&'s self, kv: KeyValFmt<'b>) -> KeyValFmt<'b> {
+        let mut kv = self.machine.fmt_key_val(kv).add("path", Some(self.path()));
+        for (n, arg) in self.argv() {
+            kv = kv.add(Argv(*n), Some(arg));
+        }
+
+        kv
+    }
+}
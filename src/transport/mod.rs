@@ -1,25 +1,23 @@
 //! D-Bus supported transports.
 
-use std::{borrow::Cow, fmt};
+use alloc::borrow::Cow;
+use core::fmt;
 
 use super::{percent, DBusAddr, Error, KeyValFmt, Result};
 
 mod autolaunch;
-pub use autolaunch::Autolaunch;
-#[cfg(target_os = "windows")]
-pub use autolaunch::AutolaunchScope;
+pub use autolaunch::{Autolaunch, AutolaunchScope};
 
-#[cfg(target_os = "macos")]
 mod launchd;
-#[cfg(target_os = "macos")]
 pub use launchd::Launchd;
 
+mod machine;
+pub use machine::{MachineExec, MachineRef, MachineUnix};
+
 mod nonce_tcp;
 pub use nonce_tcp::NonceTcp;
 
-#[cfg(target_os = "linux")]
 mod systemd;
-#[cfg(target_os = "linux")]
 pub use systemd::Systemd;
 
 mod tcp;
@@ -29,21 +27,38 @@ mod unix;
 pub use unix::{Unix, UnixAddrKind};
 
 mod unixexec;
+#[cfg(feature = "std")]
+pub use unixexec::SshTunnel;
 pub use unixexec::Unixexec;
 
 mod vsock;
 pub use vsock::Vsock;
 
+// The transports known to the parser, and the characters used to generate other transport names.
+#[cfg(any(test, feature = "arbitrary", feature = "proptest"))]
+pub(crate) const KNOWN_TRANSPORTS: &[&str] = &[
+    "unix",
+    "launchd",
+    "systemd",
+    "tcp",
+    "nonce-tcp",
+    "unixexec",
+    "autolaunch",
+    "vsock",
+    "x-machine-unix",
+    "x-machine-exec",
+];
+#[cfg(any(test, feature = "arbitrary", feature = "proptest"))]
+pub(crate) const TRANSPORT_NAME_CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789-_.";
+
 /// A D-Bus transport.
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum Transport<'a> {
     /// Unix Domain Sockets transport.
     Unix(unix::Unix<'a>),
-    #[cfg(target_os = "macos")]
     /// launchd transport.
     Launchd(launchd::Launchd<'a>),
-    #[cfg(target_os = "linux")]
     /// systemd transport.
     Systemd(systemd::Systemd<'a>),
     /// TCP Sockets transport.
@@ -56,6 +71,10 @@ pub enum Transport<'a> {
     Autolaunch(autolaunch::Autolaunch<'a>),
     /// VSOCK Sockets transport.
     Vsock(vsock::Vsock<'a>),
+    /// sd-bus Unix socket inside a local container transport.
+    MachineUnix(machine::MachineUnix<'a>),
+    /// sd-bus executed subprocess inside a local container transport.
+    MachineExec(machine::MachineExec<'a>),
     Other(Cow<'a, str>),
 }
 
@@ -64,33 +83,53 @@ impl<'a> Transport<'a> {
     pub fn into_owned(self) -> Transport<'static> {
         match self {
             Transport::Unix(unix) => Transport::Unix(unix.into_owned()),
-            #[cfg(target_os = "macos")]
             Transport::Launchd(launchd) => Transport::Launchd(launchd.into_owned()),
-            #[cfg(target_os = "linux")]
             Transport::Systemd(systemd) => Transport::Systemd(systemd.into_owned()),
             Transport::Tcp(tcp) => Transport::Tcp(tcp.into_owned()),
             Transport::NonceTcp(nonce_tcp) => Transport::NonceTcp(nonce_tcp.into_owned()),
             Transport::Unixexec(unixexec) => Transport::Unixexec(unixexec.into_owned()),
             Transport::Autolaunch(autolaunch) => Transport::Autolaunch(autolaunch.into_owned()),
             Transport::Vsock(vsock) => Transport::Vsock(vsock.into_owned()),
+            Transport::MachineUnix(m) => Transport::MachineUnix(m.into_owned()),
+            Transport::MachineExec(m) => Transport::MachineExec(m.into_owned()),
             Transport::Other(other) => Transport::Other(other.into_owned().into()),
         }
     }
+
+    /// Whether the transport can only be used to listen, and clients can't connect to it.
+    ///
+    /// This is the case of `unix:` `dir`, `tmpdir` and `runtime` addresses, `systemd:`, and TCP or
+    /// VSOCK addresses without a port, or a `nonce-tcp:` address without a `noncefile`.
+    pub fn is_listen_only(&self) -> bool {
+        match self {
+            Transport::Unix(unix) => !matches!(
+                unix.kind(),
+                UnixAddrKind::Path(_) | UnixAddrKind::Abstract(_)
+            ),
+            Transport::Systemd(_) => true,
+            Transport::Tcp(tcp) => matches!(tcp.port(), None | Some(0)),
+            Transport::NonceTcp(nonce_tcp) => {
+                matches!(nonce_tcp.port(), None | Some(0)) || nonce_tcp.noncefile().is_none()
+            }
+            Transport::Vsock(vsock) => vsock.cid().is_none() || vsock.port().is_none(),
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Display for Transport<'_> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Unix(_) => write!(f, "unix"),
-            #[cfg(target_os = "macos")]
             Self::Launchd(_) => write!(f, "launchd"),
-            #[cfg(target_os = "linux")]
             Self::Systemd(_) => write!(f, "systemd"),
             Self::Tcp(_) => write!(f, "tcp"),
             Self::NonceTcp(_) => write!(f, "nonce-tcp"),
             Self::Unixexec(_) => write!(f, "unixexec"),
             Self::Autolaunch(_) => write!(f, "autolaunch"),
             Self::Vsock(_) => write!(f, "vsock"),
+            Self::MachineUnix(_) => write!(f, "x-machine-unix"),
+            Self::MachineExec(_) => write!(f, "x-machine-exec"),
             Self::Other(o) => write!(f, "{o}"),
         }
     }
@@ -110,15 +149,15 @@ impl<'a> TransportImpl<'a> for Transport<'a> {
         let col = s.addr.find(':').ok_or(Error::MissingTransport)?;
         match &s.addr[..col] {
             "unix" => Ok(Self::Unix(Unix::for_address(s)?)),
-            #[cfg(target_os = "macos")]
             "launchd" => Ok(Self::Launchd(Launchd::for_address(s)?)),
-            #[cfg(target_os = "linux")]
             "systemd" => Ok(Self::Systemd(Systemd::for_address(s)?)),
             "tcp" => Ok(Self::Tcp(Tcp::for_address(s)?)),
             "nonce-tcp" => Ok(Self::NonceTcp(NonceTcp::for_address(s)?)),
             "unixexec" => Ok(Self::Unixexec(Unixexec::for_address(s)?)),
             "autolaunch" => Ok(Self::Autolaunch(Autolaunch::for_address(s)?)),
             "vsock" => Ok(Self::Vsock(Vsock::for_address(s)?)),
+            "x-machine-unix" => Ok(Self::MachineUnix(MachineUnix::for_address(s)?)),
+            "x-machine-exec" => Ok(Self::MachineExec(MachineExec::for_address(s)?)),
             o => Ok(Self::Other(Cow::Borrowed(o))),
         }
     }
@@ -126,15 +165,15 @@ impl<'a> TransportImpl<'a> for Transport<'a> {
     fn fmt_key_val<'s: 'b, 'b>(&'s self, kv: KeyValFmt<'b>) -> KeyValFmt<'b> {
         match self {
             Self::Unix(t) => t.fmt_key_val(kv),
-            #[cfg(target_os = "macos")]
             Self::Launchd(t) => t.fmt_key_val(kv),
-            #[cfg(target_os = "linux")]
             Self::Systemd(t) => t.fmt_key_val(kv),
             Self::Tcp(t) => t.fmt_key_val(kv),
             Self::NonceTcp(t) => t.fmt_key_val(kv),
             Self::Unixexec(t) => t.fmt_key_val(kv),
             Self::Autolaunch(t) => t.fmt_key_val(kv),
             Self::Vsock(t) => t.fmt_key_val(kv),
+            Self::MachineUnix(t) => t.fmt_key_val(kv),
+            Self::MachineExec(t) => t.fmt_key_val(kv),
             Self::Other(_) => kv,
         }
     }
@@ -1,9 +1,7 @@
-use std::{borrow::Cow, ffi::OsStr};
+use alloc::borrow::Cow;
 
 use super::{
-    percent::{decode_percents_os_str, decode_percents_str, EncOsStr},
-    tcp::TcpFamily,
-    DBusAddr, Error, KeyValFmt, Result, TransportImpl,
+    percent::decode_percents_str, tcp::TcpFamily, DBusAddr, Error, KeyValFmt, Result, TransportImpl,
 };
 
 /// `nonce-tcp:` D-Bus transport.
@@ -15,10 +13,26 @@ pub struct NonceTcp<'a> {
     bind: Option<Cow<'a, str>>,
     port: Option<u16>,
     family: Option<TcpFamily>,
-    noncefile: Option<Cow<'a, OsStr>>,
+    noncefile: Option<Cow<'a, str>>,
 }
 
 impl<'a> NonceTcp<'a> {
+    pub(crate) fn new(
+        host: Option<Cow<'a, str>>,
+        bind: Option<Cow<'a, str>>,
+        port: Option<u16>,
+        family: Option<TcpFamily>,
+        noncefile: Option<Cow<'a, str>>,
+    ) -> Self {
+        Self {
+            host,
+            bind,
+            port,
+            family,
+            noncefile,
+        }
+    }
+
     /// If set, the DNS name or IP address.
     pub fn host(&self) -> Option<&str> {
         self.host.as_ref().map(|v| v.as_ref())
@@ -50,7 +64,7 @@ impl<'a> NonceTcp<'a> {
     /// If set, the nonce file location.
     ///
     /// File location containing the secret. This is only meaningful in connectable addresses.
-    pub fn noncefile(&self) -> Option<&OsStr> {
+    pub fn noncefile(&self) -> Option<&str> {
         self.noncefile.as_ref().map(|v| v.as_ref())
     }
 
@@ -64,13 +78,13 @@ impl<'a> NonceTcp<'a> {
             noncefile,
         } = self;
 
-        NonceTcp {
-            host: host.map(|h| h.into_owned().into()),
-            bind: bind.map(|b| b.into_owned().into()),
+        NonceTcp::new(
+            host.map(|h| h.into_owned().into()),
+            bind.map(|b| b.into_owned().into()),
             port,
             family,
-            noncefile: noncefile.map(|n| n.into_owned().into()),
-        }
+            noncefile.map(|n| n.into_owned().into()),
+        )
     }
 }
 
@@ -96,7 +110,7 @@ impl<'a> TransportImpl<'a> for NonceTcp<'a> {
                     res.family = Some(decode_percents_str(v)?.as_ref().try_into()?);
                 }
                 ("noncefile", Some(v)) => {
-                    res.noncefile = Some(decode_percents_os_str(v)?);
+                    res.noncefile = Some(decode_percents_str(v)?);
                 }
                 _ => continue,
             }
@@ -110,6 +124,6 @@ impl<'a> TransportImpl<'a> for NonceTcp<'a> {
             .add("bind", self.bind())
             .add("port", self.port())
             .add("family", self.family())
-            .add("noncefile", self.noncefile().map(EncOsStr))
+            .add("noncefile", self.noncefile())
     }
 }
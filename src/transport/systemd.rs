@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use core::marker::PhantomData;
 
 use super::{DBusAddr, KeyValFmt, Result, TransportImpl};
 
@@ -12,6 +12,12 @@ pub struct Systemd<'a> {
 }
 
 impl<'a> Systemd<'a> {
+    pub(crate) fn new() -> Self {
+        Systemd {
+            phantom: PhantomData,
+        }
+    }
+
     /// Convert into owned version, with 'static lifetime.
     pub fn into_owned(&self) -> Systemd<'static> {
         Systemd {
@@ -22,9 +28,7 @@ impl<'a> Systemd<'a> {
 
 impl<'a> TransportImpl<'a> for Systemd<'a> {
     fn for_address(_addr: &'a DBusAddr<'a>) -> Result<Self> {
-        Ok(Systemd {
-            phantom: PhantomData,
-        })
+        Ok(Systemd::new())
     }
 
     fn fmt_key_val<'s: 'b, 'b>(&'s self, kv: KeyValFmt<'b>) -> KeyValFmt<'b> {
@@ -1,4 +1,5 @@
-use std::{borrow::Cow, fmt};
+use alloc::borrow::Cow;
+use core::fmt;
 
 use super::{percent::decode_percents_str, DBusAddr, Error, KeyValFmt, Result, TransportImpl};
 
@@ -14,6 +15,20 @@ pub struct Tcp<'a> {
 }
 
 impl<'a> Tcp<'a> {
+    pub(crate) fn new(
+        host: Option<Cow<'a, str>>,
+        bind: Option<Cow<'a, str>>,
+        port: Option<u16>,
+        family: Option<TcpFamily>,
+    ) -> Self {
+        Self {
+            host,
+            bind,
+            port,
+            family,
+        }
+    }
+
     /// If set, DNS name or IP address.
     pub fn host(&self) -> Option<&str> {
         self.host.as_ref().map(|v| v.as_ref())
@@ -44,12 +59,12 @@ impl<'a> Tcp<'a> {
 
     /// Convert into owned version, with 'static lifetime.
     pub fn into_owned(self) -> Tcp<'static> {
-        Tcp {
-            host: self.host.map(|h| h.into_owned().into()),
-            bind: self.bind.map(|b| b.into_owned().into()),
-            port: self.port,
-            family: self.family,
-        }
+        Tcp::new(
+            self.host.map(|h| h.into_owned().into()),
+            self.bind.map(|b| b.into_owned().into()),
+            self.port,
+            self.family,
+        )
     }
 }
 
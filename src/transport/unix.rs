@@ -1,7 +1,7 @@
-use std::{borrow::Cow, ffi::OsStr};
+use alloc::borrow::Cow;
 
 use super::{
-    percent::{decode_percents, decode_percents_os_str, decode_percents_str, EncData, EncOsStr},
+    percent::{decode_percents, decode_percents_str, EncData},
     DBusAddr, Error, KeyValFmt, Result, TransportImpl,
 };
 
@@ -14,6 +14,10 @@ pub struct Unix<'a> {
 }
 
 impl<'a> Unix<'a> {
+    pub(crate) fn new(kind: UnixAddrKind<'a>) -> Self {
+        Self { kind }
+    }
+
     /// One of the various `unix:` addresses.
     pub fn kind(&self) -> &UnixAddrKind<'a> {
         &self.kind
@@ -35,7 +39,7 @@ impl<'a> TransportImpl<'a> for Unix<'a> {
             match k {
                 "path" | "dir" | "tmpdir" => {
                     let v = v.ok_or_else(|| Error::MissingValue(k.into()))?;
-                    let v = decode_percents_os_str(v)?;
+                    let v = decode_percents_str(v)?;
                     kind = Some(match k {
                         "path" => UnixAddrKind::Path(v),
                         "dir" => UnixAddrKind::Dir(v),
@@ -80,7 +84,7 @@ impl<'a> TransportImpl<'a> for Unix<'a> {
             }
         }
 
-        Ok(Unix { kind })
+        Ok(Unix::new(kind))
     }
 
     fn fmt_key_val<'s: 'b, 'b>(&'s self, kv: KeyValFmt<'b>) -> KeyValFmt<'b> {
@@ -93,14 +97,14 @@ impl<'a> TransportImpl<'a> for Unix<'a> {
 #[non_exhaustive]
 pub enum UnixAddrKind<'a> {
     /// Path of the unix domain socket.
-    Path(Cow<'a, OsStr>),
+    Path(Cow<'a, str>),
     /// Directory in which a socket file with a random file name starting with 'dbus-' should be
     /// created by a server.
-    Dir(Cow<'a, OsStr>),
+    Dir(Cow<'a, str>),
     /// The same as "dir", except that on platforms with abstract sockets, a server may attempt to
     /// create an abstract socket whose name starts with this directory instead of a path-based
     /// socket.
-    Tmpdir(Cow<'a, OsStr>),
+    Tmpdir(Cow<'a, str>),
     /// Unique string in the abstract namespace, often syntactically resembling a path but
     /// unconnected to the filesystem namespace
     Abstract(Cow<'a, [u8]>),
@@ -111,9 +115,9 @@ pub enum UnixAddrKind<'a> {
 impl UnixAddrKind<'_> {
     fn fmt_key_val<'s: 'b, 'b>(&'s self, kv: KeyValFmt<'b>) -> KeyValFmt<'b> {
         match self {
-            UnixAddrKind::Path(p) => kv.add("path", Some(EncOsStr(p))),
-            UnixAddrKind::Dir(p) => kv.add("dir", Some(EncOsStr(p))),
-            UnixAddrKind::Tmpdir(p) => kv.add("tmpdir", Some(EncOsStr(p))),
+            UnixAddrKind::Path(p) => kv.add("path", Some(p)),
+            UnixAddrKind::Dir(p) => kv.add("dir", Some(p)),
+            UnixAddrKind::Tmpdir(p) => kv.add("tmpdir", Some(p)),
             UnixAddrKind::Abstract(p) => kv.add("abstract", Some(EncData(p))),
             UnixAddrKind::Runtime => kv.add("runtime", Some("yes")),
         }
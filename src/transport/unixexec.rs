@@ -1,26 +1,44 @@
-use std::{borrow::Cow, ffi::OsStr, fmt};
-
-use super::{
-    percent::{decode_percents_os_str, decode_percents_str, EncOsStr},
-    DBusAddr, Error, KeyValFmt, Result, TransportImpl,
+use alloc::{borrow::Cow, vec::Vec};
+#[cfg(feature = "std")]
+use alloc::{
+    format,
+    string::{String, ToString},
 };
+use core::fmt;
+
+use super::{percent::decode_percents_str, DBusAddr, Error, KeyValFmt, Result, TransportImpl};
+#[cfg(feature = "std")]
+use crate::{BusType, DBusAddrList};
+
+// The bridge between standard I/O and a bus, run on the remote host.
+#[cfg(feature = "std")]
+const STDIO_BRIDGE: &str = "systemd-stdio-bridge";
+
+// The ssh options taking a value, from ssh(1).
+#[cfg(feature = "std")]
+const SSH_VALUE_OPTIONS: &[u8] = b"BbcDEeFIiJLlmOoPpQRSWw";
 
 /// `unixexec:` D-Bus transport.
 ///
 /// <https://dbus.freedesktop.org/doc/dbus-specification.html#transports-exec>
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Unixexec<'a> {
-    path: Cow<'a, OsStr>,
+    path: Cow<'a, str>,
     argv: Vec<(usize, Cow<'a, str>)>,
 }
 
 impl<'a> Unixexec<'a> {
+    // `argv` must be sorted by index, as when parsed.
+    pub(crate) fn new(path: Cow<'a, str>, argv: Vec<(usize, Cow<'a, str>)>) -> Self {
+        Self { path, argv }
+    }
+
     /// Binary to execute.
     ///
     /// Path of the binary to execute, either an absolute path or a binary name that is searched for
     /// in the default search path of the OS. This corresponds to the first argument of execlp().
     /// This key is mandatory.
-    pub fn path(&self) -> &OsStr {
+    pub fn path(&self) -> &str {
         self.path.as_ref()
     }
 
@@ -31,6 +49,189 @@ impl<'a> Unixexec<'a> {
         self.argv.as_ref()
     }
 
+    /// A tunnel to a bus of a remote host, over ssh.
+    ///
+    /// `systemd-stdio-bridge` is run on the remote host, connected to the system bus, the session
+    /// bus of the remote user, or the given address. The starter bus can't be reached.
+    ///
+    /// Example:
+    /// ```
+    /// use dbus_addr::{
+    ///     transport::{Transport, Unixexec},
+    ///     BusType, OwnedDBusAddr,
+    /// };
+    ///
+    /// let ssh = Unixexec::ssh("example.com", Some("alice"), &BusType::Session).unwrap();
+    /// let addr = OwnedDBusAddr::from(Transport::Unixexec(ssh));
+    /// assert_eq!(
+    ///     addr.to_string(),
+    ///     "unixexec:path=ssh,argv1=-xT,argv2=--,argv3=alice%40example.com,\
+    ///      argv4=systemd-stdio-bridge,argv5=--user"
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn ssh(host: &str, user: Option<&str>, remote_bus: &BusType) -> Result<Unixexec<'static>> {
+        if host.is_empty() || host.contains('@') {
+            return Err(Error::InvalidValue("host".into()));
+        }
+        let bridge_arg = match remote_bus {
+            BusType::System => None,
+            BusType::Session => Some("--user".to_string()),
+            // the remote command is run by a shell
+            BusType::Address(addr) => {
+                addr.validate()?;
+                Some(format!(
+                    "--bus-path={}",
+                    crate::shell::word_quote(&addr.to_string())
+                ))
+            }
+            BusType::Starter => {
+                return Err(Error::Other(
+                    "the starter bus of a remote host can't be reached".into(),
+                ))
+            }
+        };
+        let destination = match user {
+            Some(user) if user.is_empty() || user.contains('@') => {
+                return Err(Error::InvalidValue("user".into()));
+            }
+            Some(user) => format!("{user}@{host}"),
+            None => host.to_string(),
+        };
+
+        let argv = [
+            "-xT".to_string(),
+            "--".to_string(),
+            destination,
+            STDIO_BRIDGE.to_string(),
+        ]
+        .into_iter()
+        .chain(bridge_arg)
+        .enumerate()
+        .map(|(n, arg)| (n + 1, Cow::Owned(arg)))
+        .collect();
+
+        Ok(Unixexec::new(Cow::Borrowed("ssh"), argv))
+    }
+
+    /// The remote host and bus, if this is an ssh tunnel as built by [`Self::ssh`].
+    ///
+    /// Other ssh options are allowed before the destination.
+    #[cfg(feature = "std")]
+    pub fn ssh_tunnel(&self) -> Option<SshTunnel> {
+        if path_file_name(self.path()) != Some("ssh") {
+            return None;
+        }
+        // consecutive arguments, from `argv1`
+        let mut args = Vec::new();
+        for (i, (n, arg)) in self.argv().iter().filter(|(n, _)| *n != 0).enumerate() {
+            if *n != i + 1 {
+                return None;
+            }
+            args.push(arg.as_ref());
+        }
+
+        let mut args = args.into_iter();
+        let mut user = None;
+        let destination = loop {
+            let arg = args.next()?;
+            if arg == "--" {
+                break args.next()?;
+            }
+            let Some(opts) = arg.strip_prefix('-') else {
+                break arg;
+            };
+            // `-xT`, `-p22`, `-p 22`
+            for (i, opt) in opts.bytes().enumerate() {
+                if !SSH_VALUE_OPTIONS.contains(&opt) {
+                    continue;
+                }
+                let value = match &opts[i + 1..] {
+                    "" => args.next()?,
+                    value => value,
+                };
+                if opt == b'l' {
+                    user = Some(value);
+                }
+                break;
+            }
+        };
+        let (user, host) = match destination.split_once('@') {
+            Some((user, host)) => (Some(user), host),
+            None => (user, destination),
+        };
+        if host.is_empty() || host.starts_with('-') {
+            return None;
+        }
+
+        let bridge = args.next()?;
+        if bridge != STDIO_BRIDGE && !bridge.ends_with("/systemd-stdio-bridge") {
+            return None;
+        }
+        let remote_bus = match (args.next(), args.next()) {
+            (None | Some("--system"), None) => BusType::System,
+            (Some("--user"), None) => BusType::Session,
+            (Some("--bus-path" | "-p"), Some(addr)) => bus_address(&unquote(addr)?)?,
+            (Some(arg), None) => bus_address(unquote(arg)?.strip_prefix("--bus-path=")?)?,
+            _ => return None,
+        };
+        if args.next().is_some() {
+            return None;
+        }
+
+        Some(SshTunnel {
+            host: host.to_string(),
+            user: user.map(str::to_string),
+            remote_bus,
+        })
+    }
+
+    /// Parse a shell command line, such as `ssh -xT host systemd-stdio-bridge`.
+    ///
+    /// The first word is the `path`, and the next ones `argv1`, `argv2`... Single and double
+    /// quotes, backslash escapes and comments are supported, as in a POSIX shell, but there is no
+    /// expansion nor redirection: `$`, `*` or `|` are kept as is.
+    ///
+    /// Example:
+    /// ```
+    /// use dbus_addr::transport::Unixexec;
+    ///
+    /// let exec = Unixexec::from_command_line("ssh -xT 'my host' systemd-stdio-bridge").unwrap();
+    /// assert_eq!(exec.path(), "ssh");
+    /// assert_eq!(exec.argv()[1].1, "my host");
+    /// assert_eq!(
+    ///     exec.to_command_line(),
+    ///     "ssh -xT 'my host' systemd-stdio-bridge"
+    /// );
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn from_command_line(s: &str) -> Result<Unixexec<'static>> {
+        let mut words = crate::shell::split_command_line(s)?.into_iter();
+        let path = words.next().unwrap_or_default();
+        if path.is_empty() {
+            return Err(Error::InvalidValue("path".into()));
+        }
+        let argv = words
+            .enumerate()
+            .map(|(n, arg)| (n + 1, Cow::Owned(arg)))
+            .collect();
+
+        Ok(Unixexec::new(Cow::Owned(path), argv))
+    }
+
+    /// The command line, quoted for a POSIX shell.
+    ///
+    /// The `path` comes first, then the arguments from `argv1`, in order. `argv0` is left out,
+    /// since a shell sets it to the command name.
+    #[cfg(feature = "std")]
+    pub fn to_command_line(&self) -> String {
+        let argv = self.argv().iter().filter(|(n, _)| *n != 0);
+
+        crate::shell::join_command_line(
+            core::iter::once(self.path()).chain(argv.map(|(_, arg)| arg.as_ref())),
+        )
+    }
+
     /// Convert into owned version, with 'static lifetime.
     pub fn into_owned(self) -> Unixexec<'static> {
         let argv = self
@@ -53,7 +254,7 @@ impl<'a> TransportImpl<'a> for Unixexec<'a> {
         for (k, v) in s.key_val_iter() {
             match (k, v) {
                 ("path", Some(v)) => {
-                    path = Some(decode_percents_os_str(v)?);
+                    path = Some(decode_percents_str(v)?);
                 }
                 (k, Some(v)) if k.starts_with("argv") => {
                     let n: usize = k[4..].parse().map_err(|_| Error::InvalidValue(k.into()))?;
@@ -70,11 +271,11 @@ impl<'a> TransportImpl<'a> for Unixexec<'a> {
 
         argv.sort_by_key(|(num, _)| *num);
 
-        Ok(Self { path, argv })
+        Ok(Self::new(path, argv))
     }
 
     fn fmt_key_val<'s: 'b, 'b>(&'s self, mut kv: KeyValFmt<'b>) -> KeyValFmt<'b> {
-        kv = kv.add("path", Some(EncOsStr(self.path())));
+        kv = kv.add("path", Some(self.path()));
         for (n, arg) in self.argv() {
             kv = kv.add(Argv(*n), Some(arg));
         }
@@ -83,8 +284,9 @@ impl<'a> TransportImpl<'a> for Unixexec<'a> {
     }
 }
 
+// The `argvN` key.
 #[derive(Debug, PartialEq, Eq)]
-struct Argv(usize);
+pub(super) struct Argv(pub(super) usize);
 
 impl fmt::Display for Argv {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -93,3 +295,56 @@ impl fmt::Display for Argv {
         write!(f, "argv{n}")
     }
 }
+
+/// An ssh tunnel to a remote bus, see [`Unixexec::ssh`].
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SshTunnel {
+    host: String,
+    user: Option<String>,
+    remote_bus: BusType,
+}
+
+#[cfg(feature = "std")]
+impl SshTunnel {
+    /// The remote host.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The remote user, if given.
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// The bus of the remote host.
+    pub fn remote_bus(&self) -> &BusType {
+        &self.remote_bus
+    }
+}
+
+// A remote command argument, unquoted as by the remote shell.
+#[cfg(feature = "std")]
+fn unquote(arg: &str) -> Option<String> {
+    let mut words = crate::shell::split_command_line(arg).ok()?;
+    if words.len() != 1 {
+        return None;
+    }
+
+    Some(words.remove(0))
+}
+
+// A valid `--bus-path` address.
+#[cfg(feature = "std")]
+fn bus_address(addr: &str) -> Option<BusType> {
+    let addr = DBusAddrList::try_from(addr.to_string()).ok()?;
+    addr.validate().ok()?;
+
+    Some(BusType::Address(addr))
+}
+
+// The last component of a path.
+#[cfg(feature = "std")]
+fn path_file_name(path: &str) -> Option<&str> {
+    path.rsplit('/').next()
+}
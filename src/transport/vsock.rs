@@ -1,4 +1,4 @@
-use std::marker::PhantomData;
+use core::marker::PhantomData;
 
 use super::{percent::decode_percents_str, DBusAddr, Error, KeyValFmt, Result, TransportImpl};
 
@@ -14,6 +14,14 @@ pub struct Vsock<'a> {
 }
 
 impl<'a> Vsock<'a> {
+    pub(crate) fn new(cid: Option<u32>, port: Option<u32>) -> Self {
+        Self {
+            cid,
+            port,
+            phantom: PhantomData,
+        }
+    }
+
     /// The VSOCK port.
     pub fn port(&self) -> Option<u32> {
         self.port
@@ -59,11 +67,7 @@ impl<'a> TransportImpl<'a> for Vsock<'a> {
             }
         }
 
-        Ok(Vsock {
-            port,
-            cid,
-            phantom: PhantomData,
-        })
+        Ok(Vsock::new(cid, port))
     }
 
     fn fmt_key_val<'s: 'b, 'b>(&'s self, kv: KeyValFmt<'b>) -> KeyValFmt<'b> {